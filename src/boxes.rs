@@ -1,39 +1,84 @@
-use core::panic;
-use std::{path::{PathBuf, Path}, env::temp_dir, fs::{File, self, create_dir_all}, io::{Write, Read, BufReader}};
-use arcsys::{ggst::{pac::{GGSTPac, GGSTPacEntry}, jonbin::{GGSTJonBin, HitBox}}};
-use eframe::{egui::{self, Response, ComboBox, Sense, Frame}, emath::{Rect, Pos2}, epaint::{Color32, Stroke}};
-use serde::{Serialize, Deserialize};
-use serde_json;
-use std::collections::{BTreeMap};
-use anyhow::Result as AResult;
-
-use crate::open::open_file;
-
-struct Box {
-    x: String,
-    y: String,
-    w: String,
-    h: String,
+use std::{collections::BTreeMap, path::{PathBuf, Path}, fs::{self, File}, io::Write};
+use arcsys::ggst::jonbin::{GGSTJonBin, HitBox};
+use eframe::{egui::{self, Response, ComboBox, Sense, Frame, CursorIcon}, emath::{Rect, Pos2, Vec2}, epaint::{Color32, Stroke, Shape}};
+use anyhow::{anyhow, Result as AResult};
+
+use crate::collision::{CollisionData, JonbinData};
+use crate::diff::{PacDiff, diff_jonb};
+use crate::browser::JonbinBrowser;
+use crate::document::Document;
+use crate::timeline::{self, Timeline};
+use crate::merge::{self, Conflict};
+use crate::history::{Command, History};
+use crate::recovery::TabSession;
+use crate::settings::ViewState;
+use crate::save::write_with_backup;
+use crate::sprites::SpriteCache;
+
+// Where a box lives in a jonbin: the index of its group in `boxes`, and its index inside that group.
+#[derive(Copy, Clone, PartialEq)]
+pub struct BoxId {
+    pub group: usize,
+    pub index: usize,
 }
 
-#[derive(Serialize, Deserialize)]
-enum MetaKind {
-    Pac(GGSTPac),
+#[derive(Copy, Clone, PartialEq)]
+enum EditMode {
+    OffsetSize,
+    Edges,
 }
 
-impl Default for Box {
-    fn default() -> Self {
-        Self {
-            x: "0.0".to_owned(),
-            y: "0.0".to_owned(),
-            w: "0.0".to_owned(),
-            h: "0.0".to_owned(),
+#[derive(Copy, Clone, PartialEq)]
+enum DragMode {
+    Pan,
+    Move,
+    Resize { left: bool, right: bool, top: bool, bottom: bool },
+}
+
+// Distance in screen pixels from a box edge that still grabs the edge for resizing.
+const HANDLE_GRAB: f32 = 5.0;
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 20.0;
+// Boxes from the PAC being compared against are drawn in this color under the edited ones.
+const COMPARE_COLOR: Color32 = Color32::from_rgba_premultiplied(160, 160, 160, 160);
+
+// Where the boxes drawn faintly behind the selected jonbin's come from.
+#[derive(Clone, PartialEq)]
+enum Overlay {
+    None,
+    PreviousFrame,
+    // The selected jonbin as it was when the PAC was opened.
+    Original,
+    // A jonbin picked from this PAC.
+    Pinned(String),
+    // The jonbin with the same name in another open tab.
+    OtherPac(String),
+}
+
+impl Overlay {
+    fn label(&self) -> String {
+        match self {
+            Overlay::None => "None".to_string(),
+            Overlay::PreviousFrame => "Previous frame".to_string(),
+            Overlay::Original => "Unmodified original".to_string(),
+            Overlay::Pinned(name) => format!("Jonbin {}", name),
+            Overlay::OtherPac(title) => format!("Same jonbin in {}", title),
         }
     }
 }
 
-#[derive(Copy, Clone)]
-enum BoxType {
+// The jonbins of the other open tabs, by tab title.
+pub type OtherPacs<'a> = [(String, &'a BTreeMap<String, GGSTJonBin>)];
+
+// Another PAC opened next to this one to see how its collision differs.
+struct Comparison {
+    path: PathBuf,
+    doc: Document,
+    diff: PacDiff,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum BoxType {
     Hurtbox = 0,
     Hitbox = 1,
     ExPoint = 2,
@@ -83,81 +128,195 @@ impl TryFrom<u32> for BoxType {
 }
 
 pub struct BoxesWindow {
-    path: PathBuf,
-    pub jonbins: BTreeMap<String, GGSTJonBin>,
+    pub doc: Document,
     selected: String,
-    boxtype: String,
     offset_x: f32,
     offset_y: f32,
-    last_cursor_pos: Pos2,
+    zoom: f32,
+    fit_requested: bool,
+    drag_mode: DragMode,
     current_box: Option<HitBox>,
-    box_info: Box,
-    box_index: u32,
-    current_name: String,
+    edit_mode: EditMode,
+    nudge_step: f32,
+    selected_box: Option<BoxId>,
     new_name: String,
+    insert_index: usize,
+    // What the image rows are showing, and the names they were filled from.
+    image_buffer: Vec<String>,
+    image_buffer_source: Vec<String>,
+    image_drag: Option<usize>,
     jonb_name: String,
-    image_index: usize,
+    jonb_rename: String,
     pub is_gbvs: bool,
+    history: History,
+    compare: Option<Comparison>,
+    // Left over from merging our edits onto an updated PAC, waiting to be resolved.
+    conflicts: Vec<Conflict>,
+    browser: JonbinBrowser,
+    timeline: Timeline,
+    overlay: Overlay,
+    overlay_alpha: f32,
 }
 
 impl Default for BoxesWindow {
     fn default() -> Self {
         Self {
-            path: Default::default(),
-            jonbins: Default::default(),
+            doc: Default::default(),
             selected: "".to_string(),
-            boxtype: "".to_string(),
             offset_x: 640.0,
             offset_y: 802.0,
-            last_cursor_pos: Default::default(),
+            zoom: 1.0,
+            fit_requested: false,
+            drag_mode: DragMode::Pan,
             current_box: Default::default(),
-            box_info: Default::default(),
-            box_index: 0,
-            current_name: "".to_string(),
+            edit_mode: EditMode::OffsetSize,
+            nudge_step: 1.0,
+            selected_box: None,
             new_name: "".to_string(),
+            insert_index: 0,
+            image_buffer: Vec::new(),
+            image_buffer_source: Vec::new(),
+            image_drag: None,
             jonb_name: "".to_string(),
-            image_index: 0,
+            jonb_rename: "".to_string(),
             is_gbvs: false,
+            history: Default::default(),
+            compare: None,
+            conflicts: Vec::new(),
+            browser: Default::default(),
+            timeline: Default::default(),
+            overlay: Overlay::None,
+            overlay_alpha: 0.35,
+        }
+    }
+}
+
+impl BoxType {
+    pub const ALL: [BoxType; 18] = [
+        BoxType::Hurtbox,
+        BoxType::Hitbox,
+        BoxType::ExPoint,
+        BoxType::ExRect,
+        BoxType::ExVector,
+        BoxType::Push,
+        BoxType::TempCenter,
+        BoxType::Neck,
+        BoxType::Abdominal,
+        BoxType::AttackVsPush,
+        BoxType::SpGuard,
+        BoxType::RLeg,
+        BoxType::LLeg,
+        BoxType::Private0,
+        BoxType::Private1,
+        BoxType::Private2,
+        BoxType::Private3,
+        BoxType::ExtendJon,
+    ];
+
+    // For raw kinds read from a jonbin, which may not be a known type.
+    pub fn name_of(kind: u32) -> &'static str {
+        match BoxType::try_from(kind) {
+            Ok(box_type) => box_type.name(),
+            Err(_) => "Unknown",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoxType::Hurtbox => "Hurtbox",
+            BoxType::Hitbox => "Hitbox",
+            BoxType::ExPoint => "ExPoint",
+            BoxType::ExRect => "ExRect",
+            BoxType::ExVector => "ExVector",
+            BoxType::Push => "Push",
+            BoxType::TempCenter => "TempCenter",
+            BoxType::Neck => "Neck",
+            BoxType::Abdominal => "Abdominal",
+            BoxType::AttackVsPush => "AttackVsPush",
+            BoxType::SpGuard => "SpGuard",
+            BoxType::RLeg => "RLeg",
+            BoxType::LLeg => "LLeg",
+            BoxType::Private0 => "Private0",
+            BoxType::Private1 => "Private1",
+            BoxType::Private2 => "Private2",
+            BoxType::Private3 => "Private3",
+            BoxType::ExtendJon => "ExtendJon",
+        }
+    }
+
+    pub fn color(self) -> Color32 {
+        match self {
+            BoxType::Hurtbox => Color32::GREEN,
+            BoxType::Hitbox => Color32::RED,
+            BoxType::ExPoint => Color32::BLUE,
+            BoxType::ExRect => Color32::GOLD,
+            BoxType::ExVector => Color32::YELLOW,
+            BoxType::Push => Color32::DARK_BLUE,
+            BoxType::TempCenter => Color32::LIGHT_GREEN,
+            BoxType::Neck => Color32::LIGHT_RED,
+            BoxType::Abdominal => Color32::LIGHT_BLUE,
+            BoxType::AttackVsPush => Color32::LIGHT_YELLOW,
+            BoxType::SpGuard => Color32::DEBUG_COLOR,
+            BoxType::RLeg => Color32::KHAKI,
+            BoxType::LLeg => Color32::BROWN,
+            BoxType::Private0 => Color32::GRAY,
+            BoxType::Private1 => Color32::BLACK,
+            BoxType::Private2 => Color32::LIGHT_GRAY,
+            BoxType::Private3 => Color32::DARK_GRAY,
+            BoxType::ExtendJon => Color32::DARK_RED,
         }
     }
 }
 
 impl BoxesWindow {
-    pub fn ui(&mut self, ui: &mut egui::Ui) -> Response {
-        ComboBox::from_label("File list")
-        .selected_text(format!("{:?}", self.selected))
-        .width(150.0)
-        .show_ui(ui, |ui| {
-            for (name, _jonbin) in &self.jonbins {
-                if ui.selectable_label(true, name)
-                .clicked()
-                {
-                    if self.selected != ""
-                    {
-                        match self.write_jonb(){
-                            Ok(_) => true,
-                            Err(e) => panic!("Could not write jonbin! {}", e)
-                        };
-                    }
-                    self.current_box = None;
-                    self.box_index = 0;
-                    self.boxtype = "".to_string();
-                    self.selected = name.to_string();
-                    self.current_name = "".to_string();
-                };
+    pub fn ui(&mut self, ui: &mut egui::Ui, sprites: &mut SpriteCache, others: &OtherPacs) -> Response {
+        // Text fields handle their own undo while they have focus.
+        if ui.memory().focus().is_none() {
+            if ui.ctx().input_mut().consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z) {
+                self.redo();
+            }
+            else if ui.ctx().input_mut().consume_key(egui::Modifiers::COMMAND, egui::Key::Z) {
+                self.undo();
+            }
+        }
+        let switch_to = egui::SidePanel::left("jonbin_browser")
+        .resizable(true)
+        .default_width(220.0)
+        .show_inside(ui, |ui| {
+            self.browser.ui(ui, &self.doc, &self.selected)
+        }).inner;
+        if let Some(name) = switch_to {
+            self.select(&name);
+        }
+        if self.compare.is_some() {
+            self.diff_panel(ui);
+        }
+        if !self.conflicts.is_empty() {
+            self.conflict_panel(ui);
+        }
+        if self.doc.jonbins.contains_key(&self.selected) {
+            if let Some(name) = self.timeline.ui(ui, &self.doc.jonbins, &self.selected) {
+                self.select(&name);
             }
-        });
-        if self.selected != ""{
             self.box_list(ui);
-            ui.label("You can click and drag the canvas to move around!
-Right click to reset to the original position.");
+            self.overlay_picker(ui, others);
+            ui.horizontal(|ui| {
+                ui.label("You can click and drag the canvas to move around, and scroll to zoom!
+Click a box to select it, drag it to move it, and drag its edges or corners to resize it.
+Arrow keys nudge the selected box, and Shift + arrow keys resize it.
+Right click to reset to the original position and zoom.");
+                if ui.button("Zoom to fit").clicked() {
+                    self.fit_requested = true;
+                }
+                ui.label(format!("Zoom: {:.0}%", self.zoom * 100.0));
+            });
             Frame::canvas(ui.style()).show(ui, |ui| {
-                self.render_boxes(ui);
+                self.render_boxes(ui, sprites, others);
             });
         }
         else {
             ui.horizontal(|ui| {
-                ui.label("Select a file from the file list!");
+                ui.label("Select a jonbin from the list on the left!");
             });
         }
         ui.horizontal(|ui| {
@@ -167,55 +326,40 @@ Right click to reset to the original position.");
             self.create_jonb(ui);
         });
         ui.horizontal(|ui| {
-            self.rename_image(ui);
+            self.rename_jonb(ui);
         });
         ui.horizontal(|ui| {
-            self.add_image(ui);
+            self.delete_jonb(ui);
         });
-        ui.horizontal(|ui| {
-            self.remove_image(ui);
+        ui.vertical(|ui| {
+            self.image_list(ui);
         }).response
     }
 
     fn box_list(&mut self, ui: &mut egui::Ui) {
-        let jonb = self.jonbins.get(&self.selected).unwrap();
+        let jonb = match self.doc.jonbins.get(&self.selected) {
+            Some(jonb) => jonb,
+            None => return,
+        };
+        let selected_text = match self.selected_box {
+            Some(id) => match jonb.boxes.get(id.group).and_then(|group| group.get(id.index)) {
+                Some(hitbox) => box_label(hitbox, id),
+                None => "".to_string(),
+            },
+            None => "".to_string(),
+        };
         ui.horizontal(|ui| {
             ComboBox::from_label("Box list")
-            .selected_text(format!("{} #{}", self.boxtype, self.box_index))
+            .selected_text(selected_text)
             .width(150.0)
             .show_ui(ui, |ui| {
-                for boxgroup in &jonb.boxes {
+                for (group, boxgroup) in jonb.boxes.iter().enumerate() {
                     for (index, hitbox) in boxgroup.iter().enumerate() {
-                        let kind = match hitbox.kind.try_into(){
-                            Ok(BoxType::Hurtbox) => "Hurtbox",
-                            Ok(BoxType::Hitbox) => "Hitbox",
-                            Ok(BoxType::ExPoint) => "ExPoint",
-                            Ok(BoxType::ExRect) => "ExRect",
-                            Ok(BoxType::ExVector) => "ExVector",
-                            Ok(BoxType::Push) => "Push",
-                            Ok(BoxType::TempCenter) => "TempCenter",
-                            Ok(BoxType::Neck) => "Neck",
-                            Ok(BoxType::Abdominal) => "Abdominal",
-                            Ok(BoxType::AttackVsPush) => "AttackVsPush",
-                            Ok(BoxType::SpGuard) => "SpGuard",
-                            Ok(BoxType::RLeg) => "RLeg",
-                            Ok(BoxType::LLeg) => "LLeg",
-                            Ok(BoxType::Private0) => "Private0",
-                            Ok(BoxType::Private1) => "Private1",
-                            Ok(BoxType::Private2) => "Private2",
-                            Ok(BoxType::Private3) => "Private3",
-                            Ok(BoxType::ExtendJon) => "ExtendJon",
-                            Err(_) => ""
-                        };
-                        if ui.selectable_label(true, format!("{} #{}", kind, index))
+                        let id = BoxId { group, index };
+                        if ui.selectable_label(self.selected_box == Some(id), box_label(hitbox, id))
                         .clicked()
                         {
-                            self.box_index = index as u32;
-                            self.boxtype = kind.to_string();
-                            self.box_info.x = format!("{}", hitbox.rect.x_offset);
-                            self.box_info.y = format!("{}", hitbox.rect.y_offset);
-                            self.box_info.w = format!("{}", hitbox.rect.width);
-                            self.box_info.h = format!("{}", hitbox.rect.height);
+                            self.selected_box = Some(id);
                             self.current_box = Some(*hitbox);
                         };
                     }
@@ -225,52 +369,232 @@ Right click to reset to the original position.");
     }
 
     fn box_edit(&mut self, ui: &mut egui::Ui, hitbox: Option<HitBox>) {
-        match hitbox {
+        let mut hitbox = match hitbox {
+            Some(hitbox) => hitbox,
             None => {
                 ui.horizontal(|ui| {
                     ui.label("Please select a box!");
                 });
+                return;
             },
-            Some(mut hitbox) => {
-                ui.vertical(|ui| {
-                    ui.label("X Offset");
-                    ui.text_edit_singleline(&mut self.box_info.x);
-                });
-                ui.vertical(|ui| {
-                    ui.label("Y Offset");
-                    ui.text_edit_singleline(&mut self.box_info.y);
-                });
-                ui.vertical(|ui| {
-                    ui.label("Width");
-                    ui.text_edit_singleline(&mut self.box_info.w);
-                });
-                ui.vertical(|ui| {
-                    ui.label("Height");
-                    ui.text_edit_singleline(&mut self.box_info.h);
+        };
+        self.nudge(ui, &mut hitbox);
+        let mut new_type = None;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                let current_type = BoxType::try_from(hitbox.kind).ok();
+                ComboBox::from_label("Type")
+                .selected_text(BoxType::name_of(hitbox.kind))
+                .show_ui(ui, |ui| {
+                    for box_type in BoxType::ALL {
+                        let enabled = self.has_box_group(box_type);
+                        if ui.add_enabled(enabled, egui::SelectableLabel::new(current_type == Some(box_type), box_type.name())).clicked() {
+                            new_type = Some(box_type);
+                        }
+                    }
                 });
-                hitbox.rect.x_offset = match self.box_info.x.parse::<f32>() {
-                    Ok(float) => float,
-                    Err(_) => hitbox.rect.x_offset,
-                };
-                hitbox.rect.y_offset = match self.box_info.y.parse::<f32>() {
-                    Ok(float) => float,
-                    Err(_) => hitbox.rect.y_offset,
-                };
-                hitbox.rect.width = match self.box_info.w.parse::<f32>() {
-                    Ok(float) => float,
-                    Err(_) => hitbox.rect.width,
-                };
-                hitbox.rect.height = match self.box_info.h.parse::<f32>() {
-                    Ok(float) => float,
-                    Err(_) => hitbox.rect.height,
-                };
-                self.current_box = Some(hitbox);
+                if let Some(extra) = &mut hitbox.extra {
+                    ui.label("Extra");
+                    ui.add(egui::DragValue::new(extra)).on_hover_text("Granblue Fantasy Versus only");
+                }
+                ui.separator();
+                ui.radio_value(&mut self.edit_mode, EditMode::OffsetSize, "Offset and size");
+                ui.radio_value(&mut self.edit_mode, EditMode::Edges, "Edges");
+                ui.separator();
+                ui.label("Arrow key step");
+                ui.add(egui::DragValue::new(&mut self.nudge_step).clamp_range(0.1..=100.0).speed(0.1));
+            });
+            ui.horizontal(|ui| {
+                let rect = &mut hitbox.rect;
+                match self.edit_mode {
+                    EditMode::OffsetSize => {
+                        number_field(ui, "X Offset", &mut rect.x_offset, true);
+                        number_field(ui, "Y Offset", &mut rect.y_offset, true);
+                        number_field(ui, "Width", &mut rect.width, rect.width > 0.0);
+                        number_field(ui, "Height", &mut rect.height, rect.height > 0.0);
+                    },
+                    EditMode::Edges => {
+                        // Moving one edge leaves the opposite edge where it was.
+                        let mut right = rect.x_offset + rect.width;
+                        let mut bottom = rect.y_offset + rect.height;
+                        number_field(ui, "Left", &mut rect.x_offset, rect.width > 0.0);
+                        number_field(ui, "Top", &mut rect.y_offset, rect.height > 0.0);
+                        number_field(ui, "Right", &mut right, rect.width > 0.0);
+                        number_field(ui, "Bottom", &mut bottom, rect.height > 0.0);
+                        rect.width = right - rect.x_offset;
+                        rect.height = bottom - rect.y_offset;
+                    },
+                }
+            });
+            if hitbox.rect.width <= 0.0 || hitbox.rect.height <= 0.0 {
+                ui.colored_label(Color32::RED, "The box has no area! Its width and height must be above 0.");
             }
+        });
+        self.current_box = Some(hitbox);
+        if let Some(box_type) = new_type {
+            self.set_selected_box_type(box_type);
         }
     }
 
-    fn render_boxes(&mut self, ui: &mut egui::Ui) -> Response {
-        let jonb = self.jonbins.get_mut(&self.selected).unwrap();
+    // Arrow keys move the selected box, or grow and shrink it with Shift held.
+    fn nudge(&mut self, ui: &mut egui::Ui, hitbox: &mut HitBox) {
+        if ui.memory().focus().is_some() {
+            return;
+        }
+        let step = self.nudge_step;
+        let mut input = ui.input_mut();
+        for (key, x, y) in [(egui::Key::ArrowLeft, -step, 0.0), (egui::Key::ArrowRight, step, 0.0),
+            (egui::Key::ArrowUp, 0.0, -step), (egui::Key::ArrowDown, 0.0, step)] {
+            if input.consume_key(egui::Modifiers::SHIFT, key) {
+                hitbox.rect.width = (hitbox.rect.width + x).max(0.0);
+                hitbox.rect.height = (hitbox.rect.height + y).max(0.0);
+            }
+            else if input.consume_key(egui::Modifiers::NONE, key) {
+                hitbox.rect.x_offset += x;
+                hitbox.rect.y_offset += y;
+            }
+        }
+    }
+
+    fn box_at(&self, pos: Pos2) -> Option<(BoxId, HitBox)> {
+        let jonb = self.doc.jonbins.get(&self.selected)?;
+        let offset = Vec2 { x: self.offset_x, y: self.offset_y };
+        let mut found = None;
+        // Boxes are drawn in order, so the last one containing the cursor is the one on top.
+        for (group, boxgroup) in jonb.boxes.iter().enumerate() {
+            for (index, hitbox) in boxgroup.iter().enumerate() {
+                if box_rect(hitbox, offset, self.zoom).expand(HANDLE_GRAB).contains(pos) {
+                    found = Some((BoxId { group, index }, *hitbox));
+                }
+            }
+        }
+        found
+    }
+
+    fn canvas_input(&mut self, ui: &mut egui::Ui, response: &mut Response) {
+        let offset = Vec2 { x: self.offset_x, y: self.offset_y };
+
+        if response.drag_started() {
+            self.drag_mode = DragMode::Pan;
+            if let (true, Some(pos)) = (response.dragged_by(egui::PointerButton::Primary), response.interact_pointer_pos()) {
+                if let Some(mode) = self.current_box.and_then(|hitbox| handle_at(box_rect(&hitbox, offset, self.zoom), pos)) {
+                    self.drag_mode = mode;
+                }
+                else if let Some((id, hitbox)) = self.box_at(pos) {
+                    self.selected_box = Some(id);
+                    self.current_box = Some(hitbox);
+                    self.drag_mode = DragMode::Move;
+                }
+            }
+        }
+
+        if response.dragged() {
+            let delta = response.drag_delta();
+            match (self.drag_mode, self.current_box) {
+                (DragMode::Move, Some(mut hitbox)) => {
+                    hitbox.rect.x_offset += delta.x / self.zoom;
+                    hitbox.rect.y_offset += delta.y / self.zoom;
+                    self.current_box = Some(hitbox);
+                },
+                (DragMode::Resize { mut left, mut right, mut top, mut bottom }, Some(mut hitbox)) => {
+                    let mut rect = box_rect(&hitbox, offset, self.zoom);
+                    if left { rect.min.x += delta.x; }
+                    if right { rect.max.x += delta.x; }
+                    if top { rect.min.y += delta.y; }
+                    if bottom { rect.max.y += delta.y; }
+                    // An edge dragged past the opposite one becomes that edge. This is also how a new 0x0 box,
+                    // whose opposite edges are on top of each other, grows to the right or down.
+                    if rect.min.x > rect.max.x {
+                        std::mem::swap(&mut rect.min.x, &mut rect.max.x);
+                        std::mem::swap(&mut left, &mut right);
+                    }
+                    if rect.min.y > rect.max.y {
+                        std::mem::swap(&mut rect.min.y, &mut rect.max.y);
+                        std::mem::swap(&mut top, &mut bottom);
+                    }
+                    self.drag_mode = DragMode::Resize { left, right, top, bottom };
+                    hitbox.rect.x_offset = (rect.min.x - offset.x) / self.zoom;
+                    hitbox.rect.y_offset = (rect.min.y - offset.y) / self.zoom;
+                    hitbox.rect.width = rect.width() / self.zoom;
+                    hitbox.rect.height = rect.height() / self.zoom;
+                    self.current_box = Some(hitbox);
+                },
+                _ => {
+                    self.offset_x += delta.x;
+                    self.offset_y += delta.y;
+                },
+            }
+            response.mark_changed();
+        }
+        if response.drag_released() {
+            self.drag_mode = DragMode::Pan;
+        }
+
+        if response.clicked_by(egui::PointerButton::Secondary)
+        {
+            self.offset_x = 640.0;
+            self.offset_y = 802.0;
+            self.zoom = 1.0;
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let scroll = ui.input().scroll_delta.y;
+            if scroll != 0.0 {
+                // Keep the point under the cursor fixed while zooming.
+                let new_zoom = (self.zoom * (scroll * 0.002).exp()).clamp(MIN_ZOOM, MAX_ZOOM);
+                self.offset_x = pos.x - (pos.x - self.offset_x) * new_zoom / self.zoom;
+                self.offset_y = pos.y - (pos.y - self.offset_y) * new_zoom / self.zoom;
+                self.zoom = new_zoom;
+            }
+        }
+
+        if self.fit_requested {
+            self.fit_requested = false;
+            self.zoom_to_fit(response.rect);
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let mode = match self.drag_mode {
+                DragMode::Pan if !response.dragged() => self.current_box
+                    .and_then(|hitbox| handle_at(box_rect(&hitbox, offset, self.zoom), pos))
+                    .or_else(|| self.box_at(pos).map(|_| DragMode::Move)),
+                mode => Some(mode),
+            };
+            ui.output().cursor_icon = match mode {
+                Some(DragMode::Move) => CursorIcon::Move,
+                Some(DragMode::Resize { left, right, top, bottom }) => match (left || right, top || bottom) {
+                    (true, false) => CursorIcon::ResizeHorizontal,
+                    (false, true) => CursorIcon::ResizeVertical,
+                    _ if (left && top) || (right && bottom) => CursorIcon::ResizeNwSe,
+                    _ => CursorIcon::ResizeNeSw,
+                },
+                _ if response.dragged() => CursorIcon::Grabbing,
+                _ => CursorIcon::Default,
+            };
+        }
+    }
+
+    fn zoom_to_fit(&mut self, canvas: Rect) {
+        let jonb = match self.doc.jonbins.get(&self.selected) {
+            Some(jonb) => jonb,
+            None => return,
+        };
+        let bounds = jonb.boxes.iter()
+            .flatten()
+            .map(|hitbox| box_rect(hitbox, Vec2::ZERO, 1.0))
+            .reduce(|a, b| a.union(b));
+        if let Some(bounds) = bounds {
+            let margin = 20.0;
+            let zoom = ((canvas.width() - margin * 2.0) / bounds.width().max(1.0))
+                .min((canvas.height() - margin * 2.0) / bounds.height().max(1.0))
+                .clamp(MIN_ZOOM, MAX_ZOOM);
+            self.zoom = zoom;
+            self.offset_x = canvas.center().x - bounds.center().x * zoom;
+            self.offset_y = canvas.center().y - bounds.center().y * zoom;
+        }
+    }
+
+    fn render_boxes(&mut self, ui: &mut egui::Ui, sprites: &mut SpriteCache, others: &OtherPacs) -> Response {
         let (mut response, painter) = ui.allocate_painter(
             eframe::emath::Vec2 {
                 x: (ui.available_width()),
@@ -278,548 +602,882 @@ Right click to reset to the original position.");
             },
             Sense::click_and_drag()
         );
+        self.canvas_input(ui, &mut response);
 
-        if let Some(pointer_pos) = response.interact_pointer_pos() {
-            if self.last_cursor_pos != Default::default()
-            {
-                let pointer_delta = pointer_pos - self.last_cursor_pos;
-                self.offset_x += pointer_delta.x;
-                self.offset_y += pointer_delta.y;
-                response.mark_changed();
+        let offset = Vec2 { x: self.offset_x, y: self.offset_y };
+        if let (true, Some(jonb)) = (sprites.show, self.doc.jonbins.get(&self.selected)) {
+            let origin = sprites.origin;
+            for name in &jonb.names {
+                if let Some(texture) = sprites.get(ui.ctx(), name) {
+                    let size = texture.size_vec2();
+                    let min = Pos2 {
+                        x: (origin.x - size.x / 2.0) * self.zoom + self.offset_x,
+                        y: (origin.y - size.y) * self.zoom + self.offset_y,
+                    };
+                    let rect = Rect::from_min_size(min, size * self.zoom);
+                    let uv = Rect::from_min_max(Pos2::ZERO, Pos2 { x: 1.0, y: 1.0 });
+                    painter.add(Shape::image(texture.id(), rect, uv, Color32::WHITE));
+                }
             }
-            self.last_cursor_pos = pointer_pos;
         }
-        else {
-            self.last_cursor_pos = Default::default();
+
+        if let Some(other) = self.compare.as_ref().and_then(|compare| compare.doc.jonbins.get(&self.selected)) {
+            for hitbox in other.boxes.iter().flatten() {
+                painter.rect_stroke(
+                    box_rect(hitbox, offset, self.zoom),
+                    0.0,
+                    Stroke{width: 1.5, color: COMPARE_COLOR},
+                );
+            }
         }
-        if response.clicked_by(egui::PointerButton::Secondary)
-        {
-            self.offset_x = 640.0;
-            self.offset_y = 802.0;
+
+        let previous = timeline::previous_frame(&self.doc.jonbins, &self.selected)
+            .and_then(|name| self.doc.jonbins.get(name));
+        if let (true, Some(previous)) = (self.timeline.onion_skin, previous) {
+            draw_faint_boxes(&painter, previous, offset, self.zoom, 0.35);
+        }
+        let overlay = match &self.overlay {
+            Overlay::None => None,
+            Overlay::PreviousFrame => previous,
+            Overlay::Original => self.doc.original(&self.selected),
+            Overlay::Pinned(name) => self.doc.jonbins.get(name),
+            Overlay::OtherPac(title) => others.iter()
+                .find(|(other, _)| other == title)
+                .and_then(|(_, jonbins)| jonbins.get(&self.selected)),
+        };
+        if let Some(overlay) = overlay {
+            draw_faint_boxes(&painter, overlay, offset, self.zoom, self.overlay_alpha);
         }
-        
-        for boxgroup in &mut jonb.boxes {
+
+        let jonb = match self.doc.jonbins.get_mut(&self.selected) {
+            Some(jonb) => jonb,
+            None => return response,
+        };
+
+        let before = self.current_box.map(|_| clone_jonb(jonb));
+        let mut edited = false;
+        for (group, boxgroup) in jonb.boxes.iter_mut().enumerate() {
             for (index, hitbox) in boxgroup.iter_mut().enumerate() {
-                let mut color = Color32::GREEN;
-                let kind = match hitbox.kind.try_into(){
-                    Ok(BoxType::Hurtbox) => {
-                        color = Color32::GREEN;
-                        "Hurtbox"},
-                    Ok(BoxType::Hitbox) => {
-                        color = Color32::RED;
-                        "Hitbox"},
-                    Ok(BoxType::ExPoint) => {
-                        color = Color32::BLUE;
-                        "Hitbox"},
-                    Ok(BoxType::ExRect) => {
-                        color = Color32::GOLD;
-                        "ExRect"},
-                    Ok(BoxType::ExVector) => {
-                        color = Color32::YELLOW;
-                        "ExVector"},
-                    Ok(BoxType::Push) => {
-                        color = Color32::DARK_BLUE;
-                        "Push"},
-                    Ok(BoxType::TempCenter) => {
-                        color = Color32::LIGHT_GREEN;
-                        "TempCenter"},
-                    Ok(BoxType::Neck) => {
-                        color = Color32::LIGHT_RED;
-                        "Neck"},
-                    Ok(BoxType::Abdominal) => {
-                        color = Color32::LIGHT_BLUE;
-                        "Abdominal"},
-                    Ok(BoxType::AttackVsPush) => {
-                        color = Color32::LIGHT_YELLOW;
-                        "AttackVsPush"},
-                    Ok(BoxType::SpGuard) => {
-                        color = Color32::DEBUG_COLOR;
-                        "SpGuard"},
-                    Ok(BoxType::RLeg) => {
-                        color = Color32::KHAKI;
-                        "RLeg"},
-                    Ok(BoxType::LLeg) => {
-                        color = Color32::BROWN;
-                        "LLeg"},
-                    Ok(BoxType::Private0) => {
-                        color = Color32::GRAY;
-                        "LLeg"},
-                    Ok(BoxType::Private1) => {
-                        color = Color32::BLACK;
-                        "LLeg"},
-                    Ok(BoxType::Private2) => {
-                        color = Color32::LIGHT_GRAY;
-                        "LLeg"},
-                    Ok(BoxType::Private3) => {
-                        color = Color32::DARK_GRAY;
-                        "SpGuard"},
-                    Ok(BoxType::ExtendJon)  => {
-                        color = Color32::DARK_RED;
-                        "ExtendJon"},
-                    Err(_) => ""
+                let color = match BoxType::try_from(hitbox.kind) {
+                    Ok(box_type) => box_type.color(),
+                    Err(_) => Color32::WHITE,
                 };
-                if self.box_index == index as u32 && self.boxtype == kind
-                {
-                    hitbox.rect.x_offset = self.current_box.unwrap().rect.x_offset;
-                    hitbox.rect.y_offset = self.current_box.unwrap().rect.y_offset;
-                    hitbox.rect.width = self.current_box.unwrap().rect.width;
-                    hitbox.rect.height = self.current_box.unwrap().rect.height;
+                if self.selected_box == Some(BoxId { group, index }) {
+                    if let Some(current) = self.current_box {
+                        let rect = current.rect;
+                        if hitbox.rect.x_offset != rect.x_offset || hitbox.rect.y_offset != rect.y_offset
+                            || hitbox.rect.width != rect.width || hitbox.rect.height != rect.height
+                            || hitbox.extra != current.extra {
+                            edited = true;
+                        }
+                        hitbox.rect.x_offset = rect.x_offset;
+                        hitbox.rect.y_offset = rect.y_offset;
+                        hitbox.rect.width = rect.width;
+                        hitbox.rect.height = rect.height;
+                        hitbox.extra = current.extra;
+                    }
                 }
                 painter.rect_stroke(
-                    Rect { min: Pos2{x: (hitbox.rect.x_offset + self.offset_x - 1.5), 
-                        y: (hitbox.rect.y_offset + self.offset_y - 1.5)}, 
-                        max: Pos2{x: (hitbox.rect.x_offset + hitbox.rect.width + self.offset_x + 1.5 ), 
-                        y: (hitbox.rect.y_offset + hitbox.rect.height + self.offset_y + 1.5)} },
+                    box_rect(hitbox, Vec2 { x: self.offset_x, y: self.offset_y }, self.zoom).expand(1.5),
                     0.0, 
                     Stroke{width: 3.0, color},
                 );
             }
         }
+        if let Some(hitbox) = self.current_box {
+            let rect = box_rect(&hitbox, Vec2 { x: self.offset_x, y: self.offset_y }, self.zoom);
+            for handle in [rect.left_top(), rect.center_top(), rect.right_top(), rect.left_center(),
+                rect.right_center(), rect.left_bottom(), rect.center_bottom(), rect.right_bottom()] {
+                painter.rect_filled(Rect::from_center_size(handle, Vec2::splat(6.0)), 0.0, Color32::WHITE);
+            }
+        }
+        if let (true, Some(id)) = (edited, self.selected_box) {
+            let command = Command::new(&self.selected, before, Some(clone_jonb(jonb)), &self.selected, &self.selected)
+                .merge_key(format!("{}/{}:{}", self.selected, id.group, id.index));
+            self.record(command);
+        }
         response
     }
 
+    fn overlay_picker(&mut self, ui: &mut egui::Ui, others: &OtherPacs)
+    {
+        let mut choices = vec![Overlay::None, Overlay::PreviousFrame, Overlay::Original];
+        if let Overlay::Pinned(_) = self.overlay {
+            choices.push(self.overlay.clone());
+        }
+        choices.extend(others.iter().map(|(title, _)| Overlay::OtherPac(title.clone())));
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Overlay")
+            .selected_text(self.overlay.label())
+            .width(200.0)
+            .show_ui(ui, |ui| {
+                for choice in choices {
+                    let label = choice.label();
+                    ui.selectable_value(&mut self.overlay, choice, label);
+                }
+            });
+            if ui.button("Pin selected jonbin").on_hover_text("Overlay the selected jonbin while editing others").clicked() {
+                self.overlay = Overlay::Pinned(self.selected.clone());
+            }
+            ui.add(egui::Slider::new(&mut self.overlay_alpha, 0.05..=1.0).text("Opacity"));
+        });
+    }
+
+    // Names that aren't in the PAC (anymore) are ignored, like a stale row of the diff panel.
+    // An empty name clears the selection.
+    pub fn select(&mut self, name: &str)
+    {
+        if name != "" && !self.doc.jonbins.contains_key(name) {
+            return;
+        }
+        self.current_box = None;
+        self.selected_box = None;
+        self.selected = name.to_string();
+        self.image_drag = None;
+    }
+
     fn reset(&mut self)
     {
-        self.path = Default::default();
-        self.jonbins = Default::default();
+        self.doc = Default::default();
         self.selected = "".to_string();
-        self.boxtype = "".to_string();
         self.offset_x = 640.0;
         self.offset_y = 802.0;
-        self.last_cursor_pos = Default::default();
+        self.zoom = 1.0;
+        self.fit_requested = false;
+        self.drag_mode = DragMode::Pan;
         self.current_box = Default::default();
-        self.box_info = Default::default();
-        self.box_index = 0;
+        self.selected_box = None;
+        self.history.clear();
     }
 
-    pub fn add_hurtbox(&mut self)
+    fn edit_selected<F: FnOnce(&mut GGSTJonBin) -> bool>(&mut self, edit: F) -> bool
     {
-        if self.selected != ""
+        if let Some(jonb) = self.doc.jonbins.get_mut(&self.selected)
         {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
-            if !self.is_gbvs {
-                let hurtbox = HitBox {kind: 0, rect: arcsys::ggst::jonbin::Rect {x_offset: 0.0, y_offset: 0.0, width: 0.0, height: 0.0}, extra: None};
-                jonb.boxes[0].push(hurtbox);
+            let before = clone_jonb(jonb);
+            if edit(jonb) {
+                let command = Command::new(&self.selected, Some(before), Some(clone_jonb(jonb)), &self.selected, &self.selected);
+                self.record(command);
+                return true;
             }
-            else {
-                let hurtbox = HitBox {kind: 0, rect: arcsys::ggst::jonbin::Rect {x_offset: 0.0, y_offset: 0.0, width: 0.0, height: 0.0}, extra: Some(0)};
-                jonb.boxes[0].push(hurtbox);
+        }
+        false
+    }
+
+    // Every edit goes through here so the jonbins it touched can be marked as changed.
+    fn record(&mut self, command: Command)
+    {
+        self.doc.update_dirty(&command.name);
+        if let Some(old_name) = &command.renamed_from {
+            self.doc.update_dirty(old_name);
+        }
+        self.history.push(command);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.doc.is_dirty()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    pub fn undo(&mut self)
+    {
+        if let Some(command) = self.history.pop_undo() {
+            match &command.renamed_from {
+                Some(old_name) => {
+                    self.doc.rename_jonb(&command.name, old_name);
+                    self.select(&command.selected_before);
+                },
+                None => self.restore(&command.name, &command.before, &command.selected_before),
+            }
+            self.doc.update_dirty(&command.name);
+            if let Some(old_name) = &command.renamed_from {
+                self.doc.update_dirty(old_name);
             }
+            self.history.push_undone(command);
         }
     }
 
-    pub fn add_hitbox(&mut self)
+    pub fn redo(&mut self)
     {
-        if self.selected != ""
-        {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
-            if !self.is_gbvs {
-                let hitbox = HitBox {kind: 1, rect: arcsys::ggst::jonbin::Rect {x_offset: 0.0, y_offset: 0.0, width: 0.0, height: 0.0}, extra: None};
-                jonb.boxes[1].push(hitbox);
+        if let Some(command) = self.history.pop_redo() {
+            match &command.renamed_from {
+                Some(old_name) => {
+                    self.doc.rename_jonb(old_name, &command.name);
+                    self.select(&command.selected_after);
+                },
+                None => self.restore(&command.name, &command.after, &command.selected_after),
             }
-            else {
-                let hitbox = HitBox {kind: 1, rect: arcsys::ggst::jonbin::Rect {x_offset: 0.0, y_offset: 0.0, width: 0.0, height: 0.0}, extra: Some(0)};
-                jonb.boxes[1].push(hitbox);
+            self.doc.update_dirty(&command.name);
+            if let Some(old_name) = &command.renamed_from {
+                self.doc.update_dirty(old_name);
             }
+            self.history.push_redone(command);
         }
     }
 
-    pub fn remove_hurtbox(&mut self)
+    fn restore(&mut self, name: &str, state: &Option<GGSTJonBin>, selected: &str)
     {
-        if self.selected != ""
-        {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
-            let box_group = &mut jonb.boxes[0];
-            let mut index = box_group.len();
-            for hurtbox in box_group.iter().rev() {
-                index -= 1;
-                if hurtbox.kind == 0 {
-                    box_group.remove(index);
-                    break
-                }
+        match state {
+            Some(jonb) => {
+                self.doc.jonbins.insert(name.to_string(), clone_jonb(jonb));
+            },
+            None => {
+                self.doc.jonbins.remove(name);
+            },
+        }
+        self.select(selected);
+    }
+
+    pub fn has_box_group(&self, box_type: BoxType) -> bool
+    {
+        match self.doc.jonbins.get(&self.selected) {
+            Some(jonb) => jonb.boxes.len() > box_type as usize,
+            None => false,
+        }
+    }
+
+    pub fn add_box(&mut self, box_type: BoxType)
+    {
+        // Each group holds the boxes of one type, so the type doubles as the group index.
+        let group = box_type as usize;
+        let is_gbvs = self.is_gbvs;
+        let added = self.edit_selected(|jonb| {
+            if group >= jonb.boxes.len() {
+                return false;
             }
+            let extra = if is_gbvs { Some(0) } else { None };
+            let hitbox = HitBox {kind: box_type as u32, rect: arcsys::ggst::jonbin::Rect {x_offset: 0.0, y_offset: 0.0, width: 0.0, height: 0.0}, extra};
+            jonb.boxes[group].push(hitbox);
+            true
+        });
+        if added {
+            let jonb = self.doc.jonbins.get(&self.selected).unwrap();
+            let hitbox = *jonb.boxes[group].last().unwrap();
+            self.selected_box = Some(BoxId { group, index: jonb.boxes[group].len() - 1 });
+            self.current_box = Some(hitbox);
         }
     }
 
-    pub fn remove_hitbox(&mut self)
+    pub fn has_selected_box(&self) -> bool
     {
-        if self.selected != ""
-        {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
-            let box_group = &mut jonb.boxes[1];
-            let mut index = box_group.len();
-            for hitbox in box_group.iter().rev() {
-                index -= 1;
-                if hitbox.kind == 1 {
-                    box_group.remove(index);
-                    break
-                }
-            }    
+        self.selected_box.is_some()
+    }
+
+    pub fn remove_selected_box(&mut self)
+    {
+        let id = match self.selected_box {
+            Some(id) => id,
+            None => return,
+        };
+        let removed = self.edit_selected(|jonb| {
+            match jonb.boxes.get_mut(id.group) {
+                Some(boxgroup) if id.index < boxgroup.len() => {
+                    boxgroup.remove(id.index);
+                    true
+                },
+                _ => false,
+            }
+        });
+        if removed {
+            self.current_box = None;
+            self.selected_box = None;
         }
     }
-  
-    fn rename_image(&mut self, ui: &mut egui::Ui)
+
+    // The group a box is in has to match its kind, so changing the kind moves it to the end of its new group.
+    pub fn set_selected_box_type(&mut self, box_type: BoxType)
     {
-        if self.selected != "" {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
-            ui.horizontal(|ui| {
-                let mut index_str: String = self.image_index.to_string();
-                ui.label("Choose the image index!");
-                ui.text_edit_singleline(&mut index_str);
-                let prev_index = self.image_index;
-                self.image_index = match index_str.parse::<usize>() {
-                    Ok(index) => {
-                        if index != self.image_index
-                        {
-                            index
+        let (id, current) = match (self.selected_box, self.current_box) {
+            (Some(id), Some(current)) => (id, current),
+            _ => return,
+        };
+        let group = box_type as usize;
+        if current.kind == box_type as u32 && id.group == group {
+            return;
+        }
+        let moved = self.edit_selected(|jonb| {
+            if group >= jonb.boxes.len() || id.index >= jonb.boxes.get(id.group).map_or(0, |boxgroup| boxgroup.len()) {
+                return false;
+            }
+            jonb.boxes[id.group].remove(id.index);
+            jonb.boxes[group].push(HitBox { kind: box_type as u32, ..current });
+            true
+        });
+        if moved {
+            let index = self.doc.jonbins[&self.selected].boxes[group].len() - 1;
+            self.select_box(BoxId { group, index });
+        }
+    }
+
+    // Every image name of the selected jonbin as an editable row. Rows are renamed when their field
+    // loses focus, and can be dragged by their handle to reorder them.
+    fn image_list(&mut self, ui: &mut egui::Ui)
+    {
+        let names = match self.doc.jonbins.get(&self.selected) {
+            Some(jonb) => jonb.names.clone(),
+            None => return,
+        };
+        if self.image_buffer_source != names {
+            self.image_buffer = names.clone();
+            self.image_buffer_source = names.clone();
+        }
+
+        let mut rename = None;
+        let mut remove = None;
+        let mut insert = None;
+        let mut row_rects = Vec::with_capacity(names.len());
+        egui::CollapsingHeader::new(format!("Images ({})", names.len())).show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                for (index, name) in self.image_buffer.iter_mut().enumerate() {
+                    let row = ui.horizontal(|ui| {
+                        let handle = ui.add(egui::Label::new("::").sense(Sense::drag()))
+                            .on_hover_text("Drag to reorder");
+                        if handle.drag_started() {
+                            self.image_drag = Some(index);
                         }
-                        else {
-                            self.image_index
+                        if handle.hovered() || self.image_drag.is_some() {
+                            ui.output().cursor_icon = CursorIcon::Grab;
                         }
-                    },
-                    Err(_) => return,
-                };
-                if jonb.names.len() > self.image_index {
-                    if prev_index != self.image_index || self.current_name == "".to_string() {
-                        self.current_name = jonb.names[self.image_index].clone();
-                    }
-                    ui.text_edit_singleline(&mut self.current_name);
-                    if ui.button("Confirm").clicked() && self.current_name.len() <= 32 && self.current_name != "" {
-                        jonb.names[0] = self.current_name.clone();
-                        self.current_name = "".to_string();
-                    }
-                    else if self.current_name.len() > 32
-                    {
-                        ui.label("Image name too long! Must be 32 characters or less.");
-                    }
-                    else if self.current_name == "" {
-                        ui.label("Please type an image name!");
-                    }
+                        ui.label(format!("#{}", index));
+                        let field = ui.text_edit_singleline(name);
+                        let valid = image_name_valid(name);
+                        if !valid {
+                            ui.painter().rect_stroke(field.rect.expand(2.0), 2.0, Stroke { width: 1.5, color: Color32::RED });
+                            field.on_hover_text("Image names must be between 1 and 32 characters.");
+                        }
+                        else if field.lost_focus() && *name != names[index] {
+                            rename = Some((index, name.clone()));
+                        }
+                        if ui.button("Insert above").clicked() {
+                            insert = Some(index);
+                        }
+                        if ui.button("Delete").clicked() {
+                            remove = Some(index);
+                        }
+                    }).response;
+                    row_rects.push(row.rect);
                 }
-                else {
-                    if jonb.names.len() > 0
-                    {
-                        ui.label(format!("Invalid image index! The last valid index is {}", jonb.names.len() - 1));
-                    }
-                    else {
-                        ui.label("This jonbin has no images!");
-                    }
+            });
+            ui.horizontal(|ui| {
+                ui.label("New image");
+                ui.text_edit_singleline(&mut self.new_name);
+                ui.label("at");
+                ui.add(egui::DragValue::new(&mut self.insert_index).clamp_range(0..=names.len()));
+                if ui.add_enabled(image_name_valid(&self.new_name), egui::Button::new("Insert")).clicked() {
+                    insert = Some(self.insert_index.min(names.len()));
                 }
+                if self.new_name.len() > 32 {
+                    ui.label("Image name too long! Must be 32 characters or less.");
+                }
+            });
+        });
+
+        // The row under the pointer is where a dragged name lands.
+        if let Some(from) = self.image_drag {
+            let pointer = ui.input().pointer.interact_pos();
+            let target = pointer.and_then(|pos| row_rects.iter().position(|rect| pos.y < rect.bottom()))
+                .unwrap_or(names.len().saturating_sub(1));
+            if let Some(rect) = row_rects.get(target) {
+                let y = if target > from { rect.bottom() } else { rect.top() };
+                ui.painter().hline(rect.x_range(), y, Stroke { width: 2.0, color: Color32::WHITE });
+            }
+            if !ui.input().pointer.any_down() {
+                self.image_drag = None;
+                if target != from && from < names.len() && !row_rects.is_empty() {
+                    self.edit_selected(|jonb| {
+                        let name = jonb.names.remove(from);
+                        jonb.names.insert(target.min(jonb.names.len()), name);
+                        true
+                    });
+                }
+            }
+        }
+        if let Some((index, name)) = rename {
+            self.edit_selected(|jonb| {
+                jonb.names[index] = name;
+                true
+            });
+        }
+        if let Some(index) = remove {
+            self.edit_selected(|jonb| {
+                jonb.names.remove(index);
+                true
+            });
+        }
+        if let Some(index) = insert {
+            let name = if image_name_valid(&self.new_name) { self.new_name.clone() } else { "new_image".to_string() };
+            self.edit_selected(|jonb| {
+                jonb.names.insert(index, name);
+                true
             });
         }
     }
-    
-    fn add_image(&mut self, ui: &mut egui::Ui)
+
+    pub fn open_file(&mut self, path: &PathBuf) -> AResult<()> {
+        let doc = Document::open(path, self.is_gbvs)?;
+        self.reset();
+        self.doc = doc;
+        Ok(())
+    }
+
+    // What the autosave needs to bring this window back: the changed jonbins and where the view was.
+    pub fn session(&self, path: &Path, has_file: bool) -> TabSession {
+        TabSession {
+            path: path.to_path_buf(),
+            has_file,
+            is_gbvs: self.is_gbvs,
+            jonbins: self.doc.dirty_names()
+                .map(|name| (name.clone(), self.doc.jonbins.get(name).map(|jonb| GGSTJonBin::to_bytes(jonb))))
+                .collect(),
+            view: self.view(),
+        }
+    }
+
+    pub fn restore_session(session: &TabSession) -> AResult<BoxesWindow> {
+        let mut window = BoxesWindow::default();
+        window.is_gbvs = session.is_gbvs;
+        window.open_file(&session.path)?;
+        for (name, bytes) in &session.jonbins {
+            match bytes {
+                Some(bytes) => {
+                    let jonb = GGSTJonBin::parse(bytes, session.is_gbvs)
+                        .map_err(|e| anyhow!("Failed to recover {}: {}", name, e))?;
+                    window.doc.jonbins.insert(name.clone(), jonb);
+                },
+                None => {
+                    window.doc.jonbins.remove(name);
+                },
+            }
+        }
+        window.doc.refresh_dirty();
+        window.set_view(&session.view);
+        Ok(window)
+    }
+
+    pub fn view(&self) -> ViewState {
+        ViewState {
+            selected: self.selected.clone(),
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            zoom: self.zoom,
+        }
+    }
+
+    // The selection is skipped when that jonbin is gone, like after the PAC was updated.
+    pub fn set_view(&mut self, view: &ViewState) {
+        if self.doc.jonbins.contains_key(&view.selected) {
+            self.select(&view.selected);
+        }
+        self.offset_x = view.offset_x;
+        self.offset_y = view.offset_y;
+        self.zoom = view.zoom;
+    }
+
+    pub fn open_dir(&mut self, dir: &Path) -> AResult<()> {
+        let doc = Document::open_dir(dir, self.is_gbvs)?;
+        self.reset();
+        self.doc = doc;
+        Ok(())
+    }
+
+    // Writes to `path` as given, so saving always updates the file that was opened.
+    pub fn write_pac(&mut self, path: &PathBuf) -> AResult<()>
     {
-        if self.selected != "" {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
+        let compressed = self.doc.to_bytes()?;
+        write_with_backup(path, &compressed)?;
+        self.doc.mark_saved();
+        Ok(())
+    }
+
+    pub fn create_jonb(&mut self, ui: &mut egui::Ui)
+    {
+        if self.doc.jonbins.contains_key(&self.selected) {
             ui.horizontal(|ui| {
-                ui.label("Add an image!");
-                ui.text_edit_singleline(&mut self.new_name);
-                if ui.button("Confirm").clicked() && self.new_name.len() <= 32 && self.new_name != "" {
-                    jonb.names.push(self.new_name.clone());
+                ui.label("Add a jonbin using the selected one as a base!");
+                ui.text_edit_singleline(&mut self.jonb_name);
+                if ui.button("Confirm").clicked() && self.jonb_name.len() <= 32 && self.jonb_name != "" && !self.doc.name_taken(&self.jonb_name) {
+                    let jonbin = match self.doc.jonbins.get(&self.selected) {
+                        Some(jonb) => clone_jonb(jonb),
+                        None => return,
+                    };
+                    let command = Command::new(&self.jonb_name, None, Some(clone_jonb(&jonbin)), &self.selected, &self.jonb_name);
+                    self.doc.jonbins.insert(self.jonb_name.clone(), jonbin);
+                    self.select(&self.jonb_name.clone());
+                    self.record(command);
                 }
-                else if self.new_name.len() > 32
+                else if self.jonb_name.len() > 32
                 {
-                    ui.label("Image name too long! Must be 32 characters or less.");
+                    ui.label("Jonbin name too long! Must be 32 characters or less.");
+                }
+                else if self.jonb_name == "" {
+                    ui.label("Please type a jonbin name!");
                 }
-                else if self.new_name == "" {
-                    ui.label("Please type an image name!");
+                else if self.doc.name_taken(&self.jonb_name) {
+                    ui.label("A jonbin or file with that name already exists!");
                 }
             });
         }
     }
-    
-    fn remove_image(&mut self, ui: &mut egui::Ui)
+
+    fn rename_jonb(&mut self, ui: &mut egui::Ui)
     {
         if self.selected != "" {
-            let jonb = self.jonbins.get_mut(&self.selected).unwrap();
             ui.horizontal(|ui| {
-                ui.label("Remove last image!");
-                if ui.button("Confirm").clicked() && jonb.names.len() > 0 {
-                    jonb.names.remove(jonb.names.len() - 1);
+                ui.label("Rename the selected jonbin!");
+                ui.text_edit_singleline(&mut self.jonb_rename);
+                if ui.button("Confirm").clicked() && self.jonb_rename.len() <= 32 && self.jonb_rename != "" && !self.doc.name_taken(&self.jonb_rename) {
+                    let old_name = self.selected.clone();
+                    let new_name = self.jonb_rename.clone();
+                    if self.doc.rename_jonb(&old_name, &new_name) {
+                        self.select(&new_name);
+                        self.record(Command::new(&new_name, None, None, &old_name, &new_name).renamed_from(&old_name));
+                        self.jonb_rename = "".to_string();
+                    }
+                }
+                else if self.jonb_rename.len() > 32
+                {
+                    ui.label("Jonbin name too long! Must be 32 characters or less.");
+                }
+                else if self.jonb_rename == "" {
+                    ui.label("Please type a jonbin name!");
                 }
-                else if jonb.names.len() == 0 {
-                    ui.label("No images to remove!");
+                else if self.doc.name_taken(&self.jonb_rename) {
+                    ui.label("A jonbin or file with that name already exists!");
                 }
             });
         }
     }
 
-    pub fn open_file(&mut self, path: &PathBuf) -> bool {
-        let pac = open_file(&path);
-        if let Result::Ok(pac) = pac {
-            self.reset();
-            self.read_pac(path, pac);
-            self.read_jonb();
-            return true;
-        }
-        false
-    }
-  
-    fn read_pac(&mut self, path: &PathBuf, pac: GGSTPac) {
-        let mut dir = temp_dir();
-        dir.push("GGSTCollisionEditorRS");
-
-        let b: bool = Path::new(&dir).is_dir();
-        if b {
-            fs::remove_dir_all(&dir).unwrap();
-        }
-        let filename = path.file_stem().unwrap();
-        dir.push(filename.to_str().unwrap());
-
-        match create_dir_all(&dir){
-            Ok(_) => {
-                for i in &pac.files {
-                    let mut content_file = match File::create(dir.join(&i.name)){
-                        Ok(file) => file,
-                        Err(_) => {
-                            panic!("Could not create file at {:?}!", dir.join(&i.name));
-                        },  
-                    };
-                    match content_file.write_all(&i.contents)
-                    {
-                        Ok(_) => {
-                        },
-                        Err(_) => {
-                            panic!("Could not write file!");
-                        },  
-                    };
+    fn delete_jonb(&mut self, ui: &mut egui::Ui)
+    {
+        if self.selected != "" {
+            ui.horizontal(|ui| {
+                ui.label("Delete the selected jonbin!");
+                if ui.button("Confirm").clicked() {
+                    self.remove_selected_jonb();
                 }
-                
-                let meta_file = match File::create(dir.join("meta.json")) {
-                    Ok(file) => file,
-                    Err(_) => panic!("Could not create meta file!"),
-                };
-                let mut serializer = serde_json::Serializer::new(meta_file);        
-                
-                let meta = MetaKind::Pac(pac);
-                meta.serialize(&mut serializer).expect("Failed to serialize meta file!");
-                self.path = dir;
-            },
-            Err(e) => panic!("Could not create temp directory! {}", e),
-        };
+            });
+        }
     }
 
-    pub fn write_pac(&mut self, path: &PathBuf) -> AResult<()>
+    pub fn remove_selected_jonb(&mut self)
     {
-        match self.write_jonb(){
-            Ok(_) => true,
-            Err(e) => panic!("Could not write jonbin! {}", e)
-        };
-
-        let mut meta_reader = BufReader::new(File::open(self.path.join("meta.json"))?);
-        let meta: MetaKind = serde_json::from_reader(&mut meta_reader)?;
-        let meta = self.add_jonb(meta);
-        let meta = self.sort_meta(meta);
-        
-        match meta {
-            MetaKind::Pac(mut pac) => {
-                pac.files = pac
-                .files
-                .into_iter()
-                .filter_map(|mut entry| {
-                    let mut contents = Vec::new();
-                    if File::open(self.path.join(&entry.name))
-                        .and_then(|mut f| f.read_to_end(&mut contents))
-                        .is_ok()
-                    {
-                        entry.contents = contents;
-                        Some(entry)
-                    } else {
-                        println!("Failed to read {}! Excluding from PAC file", entry.name);
-                        None
-                    }
-                })
-                .collect::<Vec<GGSTPacEntry>>();
-
-                let compressed = pac.to_bytes();
-                self.write_repacked_file(path, compressed, "pac")?;
-            }
+        if let Some(jonb) = self.doc.jonbins.remove(&self.selected) {
+            let command = Command::new(&self.selected, Some(jonb), None, &self.selected, "");
+            self.select("");
+            self.record(command);
         }
+    }
 
-        Ok(())
+    pub fn export_collision(&self, path: &Path) -> AResult<()>
+    {
+        CollisionData::from_jonbins(&self.doc.jonbins).write(path)
     }
 
-    fn sort_meta(&self, mut meta: MetaKind) -> MetaKind{
-        match meta {
-            MetaKind::Pac(ref mut pac) => {
-                pac.files.sort_by(|a, b| a.unknown.cmp(&b.unknown));
-                let mut index: u32 = 0;
-                for entry in &mut pac.files {
-                    entry.id = index;
-                    index += 1;
-                }
+    // Each jonbin that the import changes becomes its own undo step.
+    pub fn import_collision(&mut self, path: &Path) -> AResult<()>
+    {
+        let data = CollisionData::read(path)?;
+        data.check(&self.doc.jonbins)?;
+        for (name, jonb_data) in &data.jonbins {
+            let jonb = self.doc.jonbins.get_mut(name).unwrap();
+            let before = clone_jonb(jonb);
+            jonb_data.apply(jonb, self.is_gbvs);
+            if JonbinData::from_jonb(&before) != JonbinData::from_jonb(jonb) {
+                let command = Command::new(name, Some(before), Some(clone_jonb(jonb)), &self.selected, &self.selected);
+                self.record(command);
             }
         }
-        
-        meta
+        let selected = self.selected.clone();
+        self.select(&selected);
+        Ok(())
     }
 
-    fn hash_names(&self, name: &str) -> u32
+    pub fn compare_with(&mut self, path: &PathBuf) -> AResult<()>
     {
-        let mut new_name = "".to_string();
-        for name_char in name.as_bytes() {
-            new_name.push((*name_char as char).to_ascii_lowercase());
-        }
-        let mut result: u32 = 0;
-        for hash_char in new_name.as_bytes() {
-            result = (*hash_char as u32).wrapping_add(137_u32.wrapping_mul(result));
-        };
-        result
-    }
-    
-    fn write_repacked_file(
-        &mut self,
-        path: &PathBuf,
-        bytes: Vec<u8>,
-        extension: &str,
-    ) -> Result<(), anyhow::Error> {
-        let write_path = path.with_extension(extension);
-        if write_path.exists() {
-            println!(
-                "{} is being overwritten!",
-                write_path.file_name().unwrap().to_string_lossy()
-            )
-        }
-        File::create(write_path)?.write_all(&bytes)?;
+        let doc = Document::open(path, self.is_gbvs)?;
+        let diff = PacDiff::new(&doc.jonbins, &self.doc.jonbins);
+        self.compare = Some(Comparison { path: path.clone(), doc, diff });
         Ok(())
     }
 
-    fn read_jonb(&mut self)
+    pub fn is_comparing(&self) -> bool
     {
-        let paths = match std::fs::read_dir(&self.path)
-        {
-            Ok(paths) => paths,
-            Err(_) => return,
-        };
-        
-        for path in paths {
-            let file = match path{
-                Ok(path) => path.path(),
-                Err(_) => continue,
-            };
-            if file.is_file() && file.file_stem().unwrap().to_str().unwrap().to_string() != "meta" { 
-                let mut file_buf = Vec::new();
-                if let Err(e) = File::open(&file)
-                .and_then(|mut f| f.read_to_end(&mut file_buf)) {
-                    println!("Error reading file {}: {}", file.display(), e);
-                    return;
-                };
-                let byte_buf = file_buf.as_slice();
-                match GGSTJonBin::parse(byte_buf, self.is_gbvs){
-                    Ok(jonb) => {
-                        let filename = file.file_stem().unwrap();
-                        self.jonbins.insert(filename.to_str().unwrap().to_string(),
-                    jonb);
-                    },
-                    Err(e) => {
-                        println!("Error reading file {}: {}", file.file_stem().unwrap().to_str().unwrap().to_string(), e);
-                        continue},
-                };
-            }
-        };
+        self.compare.is_some()
     }
 
-    pub fn create_jonb(&mut self, ui: &mut egui::Ui)
+    pub fn stop_comparing(&mut self)
     {
-        if self.selected != "" {
+        self.compare = None;
+    }
+
+    fn diff_panel(&mut self, ui: &mut egui::Ui)
+    {
+        let compare = self.compare.as_mut().unwrap();
+        let mut switch_to = None;
+        let mut stop = false;
+        let title = format!("Differences from {}", compare.path.display());
+        egui::CollapsingHeader::new(title).default_open(true).show(ui, |ui| {
             ui.horizontal(|ui| {
-                ui.label("Add a jonbin using the selected one as a base!");
-                ui.text_edit_singleline(&mut self.jonb_name);
-                if ui.button("Confirm").clicked() && self.jonb_name.len() <= 32 && self.jonb_name != "" && !self.jonbins.contains_key(&self.jonb_name){
-                    let jonbin = GGSTJonBin {
-                        names: self.jonbins.get(&self.selected).unwrap().names.clone(),
-                        version: self.jonbins.get(&self.selected).unwrap().version.clone(),
-                        editor_data: self.jonbins.get(&self.selected).unwrap().editor_data.clone(),
-                        boxes: self.jonbins.get(&self.selected).unwrap().boxes.clone(),
-                    };
-                    self.jonbins.insert(self.jonb_name.clone(), jonbin);
-                    self.selected = self.jonb_name.clone();
-                    self.write_jonb().expect("Failed to write jonbin!");
+                ui.label("Boxes from the compared PAC are drawn in gray.");
+                if ui.button("Refresh").clicked() {
+                    compare.diff = PacDiff::new(&compare.doc.jonbins, &self.doc.jonbins);
                 }
-                else if self.jonb_name.len() > 32
-                {
-                    ui.label("Jonbin name too long! Must be 32 characters or less.");
+                if ui.button("Stop comparing").clicked() {
+                    stop = true;
                 }
-                else if self.jonb_name == "" {
-                    ui.label("Please type a jonbin name!");
+            });
+            if compare.diff.is_empty() {
+                ui.label("No collision changes.");
+            }
+            egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                for name in &compare.diff.added {
+                    if ui.selectable_label(self.selected == *name, format!("+ {}", name)).clicked() {
+                        switch_to = Some(name.clone());
+                    }
+                }
+                for name in &compare.diff.removed {
+                    ui.label(format!("- {}", name));
                 }
-                else if self.jonbins.contains_key(&self.jonb_name) {
-                    ui.label("A jonbin with that name already exists!");
+                for jonb_diff in &compare.diff.changed {
+                    let label = format!("~ {} ({} boxes)", jonb_diff.name, jonb_diff.boxes.len());
+                    if ui.selectable_label(self.selected == jonb_diff.name, label).clicked() {
+                        switch_to = Some(jonb_diff.name.clone());
+                    }
                 }
             });
+            // The selected jonbin is diffed live so edits show up without a refresh.
+            let current = self.doc.jonbins.get(&self.selected);
+            let other = compare.doc.jonbins.get(&self.selected);
+            if let (Some(current), Some(other)) = (current, other) {
+                match diff_jonb(&self.selected, other, current) {
+                    Some(jonb_diff) => {
+                        ui.monospace(jonb_diff.to_string());
+                    },
+                    None => {
+                        ui.label(format!("{} is unchanged.", self.selected));
+                    },
+                }
+            }
+        });
+        if stop {
+            self.compare = None;
+        }
+        if let Some(name) = switch_to {
+            self.select(&name);
         }
     }
 
-    fn add_jonb(&mut self, mut meta: MetaKind) -> MetaKind
+    // Builds a new window holding `theirs` with the edits made from `base` to this PAC reapplied on top.
+    pub fn merge_onto(&self, base: &PathBuf, theirs: &PathBuf) -> AResult<BoxesWindow>
     {
-        let paths = match std::fs::read_dir(&self.path)
-        {
-            Ok(paths) => paths,
-            Err(_) => return meta,
-        };
-        match meta {
-            MetaKind::Pac(ref mut pac) => {
-                'outer: for path in paths {
-                    let file = match path{
-                        Ok(path) => path.path(),
-                        Err(_) => continue,
-                    };
-                    if file.is_file(){ 
-                        let name = file.file_name().unwrap()
-                        .to_str()
-                        .unwrap();
-                        if name != "meta.json"
-                        {
-                            for entry in &pac.files {
-                                if name == entry.name {
-                                    continue 'outer;
-                                }
-                                else {
-                                    continue
-                                }
-                            }
-                            let mut file_buf = Vec::new();
-                            if let Err(e) = File::open(&file).
-                            and_then(|mut f| f.read_to_end(&mut file_buf)) {
-                                println!("Error reading file {}: {}", file.display(), e);
-                                continue;
-                            };
-                            
-                            let last_file = &pac.files.last().unwrap();
-
-                            let new_file = GGSTPacEntry {
-                                unknown: self.hash_names(name),
-                                id: last_file.id + 1,
-                                name: name.to_string(),
-                                contents: file_buf,
-                            };
-
-                            pac.files.push(new_file)
+        let base = Document::open(base, self.is_gbvs)?;
+        let mut merged = BoxesWindow::default();
+        merged.is_gbvs = self.is_gbvs;
+        merged.doc = Document::open(theirs, self.is_gbvs)?;
+        merged.conflicts = merge::merge(&base.jonbins, &self.doc.jonbins, &mut merged.doc.jonbins);
+        merged.doc.refresh_dirty();
+        Ok(merged)
+    }
+
+    pub fn conflicts(&self) -> &[Conflict]
+    {
+        &self.conflicts
+    }
+
+    fn conflict_panel(&mut self, ui: &mut egui::Ui)
+    {
+        let mut go_to = None;
+        let mut resolve = None;
+        let title = format!("Merge conflicts ({})", self.conflicts.len());
+        egui::CollapsingHeader::new(title).default_open(true).show(ui, |ui| {
+            ui.label("These were changed both by the edit and by the new PAC. The new PAC's version is kept unless you pick ours.");
+            egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                for (index, conflict) in self.conflicts.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        // The new PAC may have removed the jonbin, leaving nothing to go to.
+                        if self.doc.jonbins.contains_key(conflict.jonbin()) && ui.button("Go to").clicked() {
+                            go_to = Some(index);
                         }
-                    }
+                        if ui.button("Use ours").clicked() {
+                            resolve = Some((index, true));
+                        }
+                        if ui.button("Use theirs").clicked() {
+                            resolve = Some((index, false));
+                        }
+                        ui.label(conflict.to_string());
+                    });
                 }
-            }                        
-        };
-        meta
+            });
+        });
+        if let Some(index) = go_to {
+            let conflict = &self.conflicts[index];
+            let name = conflict.jonbin().to_string();
+            let id = match conflict {
+                Conflict::Box { group, index, .. } => Some(BoxId { group: *group, index: *index }),
+                _ => None,
+            };
+            if self.doc.jonbins.contains_key(&name) {
+                self.select(&name);
+                if let Some(id) = id {
+                    self.select_box(id);
+                }
+            }
+        }
+        if let Some((index, use_ours)) = resolve {
+            self.resolve_conflict(index, use_ours);
+        }
     }
 
-    fn write_jonb(&self) -> AResult<()>{
-        if self.selected != ""
-        {
-            let write_path = self.path.join(&self.selected);
-            let bytes = GGSTJonBin::to_bytes(
-                self.jonbins.get(&self.selected).unwrap()
-            );
-            if write_path.exists() {
-                println!(
-                    "{} is being overwritten!",
-                    write_path.file_name().unwrap().to_string_lossy()
-                )
-            }
-            File::create(write_path)?.write_all(&bytes)?;
+    fn resolve_conflict(&mut self, index: usize, use_ours: bool)
+    {
+        let conflict = self.conflicts.remove(index);
+        if !use_ours {
+            return;
+        }
+        let name = conflict.jonbin().to_string();
+        let before = self.doc.jonbins.get(&name).map(clone_jonb);
+        merge::use_ours(conflict, &mut self.doc.jonbins, &mut self.conflicts);
+        let after = self.doc.jonbins.get(&name).map(clone_jonb);
+        let selected = if after.is_some() { name.clone() } else { "".to_string() };
+        self.record(Command::new(&name, before, after, &self.selected, &selected));
+        self.select(&selected);
+    }
+
+    fn select_box(&mut self, id: BoxId)
+    {
+        let hitbox = match self.doc.jonbins.get(&self.selected).and_then(|jonb| jonb.boxes.get(id.group)).and_then(|group| group.get(id.index)) {
+            Some(hitbox) => *hitbox,
+            None => return,
         };
+        self.selected_box = Some(id);
+        self.current_box = Some(hitbox);
+    }
+
+    pub fn selected_jonb(&self) -> Option<(&str, &GGSTJonBin)>
+    {
+        self.doc.jonbins.get(&self.selected).map(|jonb| (self.selected.as_str(), jonb))
+    }
+
+    // Adds a jonbin taken from another PAC or a file, renaming it if the name is already taken.
+    // Returns the name it was added under.
+    pub fn paste_jonb(&mut self, name: &str, jonb: GGSTJonBin) -> String
+    {
+        let name = unique_jonb_name(&self.doc, name);
+        let command = Command::new(&name, None, Some(clone_jonb(&jonb)), &self.selected, &name);
+        self.doc.jonbins.insert(name.clone(), jonb);
+        self.select(&name);
+        self.record(command);
+        name
+    }
+
+    pub fn export_jonb(&self, path: &PathBuf) -> AResult<()>
+    {
+        let jonb = self.doc.jonbins.get(&self.selected).ok_or_else(|| anyhow!("No jonbin is selected!"))?;
+        File::create(path)?.write_all(&GGSTJonBin::to_bytes(jonb))?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub fn import_jonb(&mut self, path: &PathBuf) -> AResult<String>
+    {
+        let bytes = fs::read(path)?;
+        let jonb = GGSTJonBin::parse(&bytes, self.is_gbvs)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(stem) => stem.to_string(),
+            None => "imported".to_string(),
+        };
+        Ok(self.paste_jonb(&name, jonb))
+    }
+
+}
+
+pub fn clone_jonb(jonb: &GGSTJonBin) -> GGSTJonBin {
+    GGSTJonBin {
+        names: jonb.names.clone(),
+        version: jonb.version.clone(),
+        editor_data: jonb.editor_data.clone(),
+        boxes: jonb.boxes.clone(),
+    }
+}
+
+// Jonbin names are limited to 32 characters, so the base name is shortened to fit the suffix.
+fn unique_jonb_name(doc: &Document, name: &str) -> String {
+    let name: String = name.chars().take(32).collect();
+    if !doc.name_taken(&name) {
+        return name;
+    }
+    let mut index = 2;
+    loop {
+        let suffix = format!("_{}", index);
+        let base: String = name.chars().take(32 - suffix.len()).collect();
+        let candidate = base + &suffix;
+        if !doc.name_taken(&candidate) {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+// A labelled number that can be dragged or typed in, outlined in red when `valid` is false.
+fn number_field(ui: &mut egui::Ui, label: &str, value: &mut f32, valid: bool) {
+    ui.vertical(|ui| {
+        ui.label(label);
+        let response = ui.add(egui::DragValue::new(value).speed(1.0).max_decimals(3));
+        if !valid {
+            ui.painter().rect_stroke(response.rect.expand(2.0), 2.0, Stroke { width: 1.5, color: Color32::RED });
+        }
+    });
+}
+
+fn image_name_valid(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 32
+}
+
+fn draw_faint_boxes(painter: &egui::Painter, jonb: &GGSTJonBin, offset: Vec2, zoom: f32, alpha: f32) {
+    for hitbox in jonb.boxes.iter().flatten() {
+        let color = match BoxType::try_from(hitbox.kind) {
+            Ok(box_type) => box_type.color(),
+            Err(_) => Color32::WHITE,
+        };
+        painter.rect_stroke(
+            box_rect(hitbox, offset, zoom),
+            0.0,
+            Stroke{width: 1.5, color: color.linear_multiply(alpha)},
+        );
+    }
+}
+
+fn box_rect(hitbox: &HitBox, offset: Vec2, zoom: f32) -> Rect {
+    Rect::from_two_pos(
+        Pos2 { x: hitbox.rect.x_offset * zoom + offset.x, y: hitbox.rect.y_offset * zoom + offset.y },
+        Pos2 { x: (hitbox.rect.x_offset + hitbox.rect.width) * zoom + offset.x, y: (hitbox.rect.y_offset + hitbox.rect.height) * zoom + offset.y },
+    )
+}
+
+fn handle_at(rect: Rect, pos: Pos2) -> Option<DragMode> {
+    if !rect.expand(HANDLE_GRAB).contains(pos) {
+        return None;
+    }
+    let left = (pos.x - rect.min.x).abs() <= HANDLE_GRAB;
+    let right = !left && (pos.x - rect.max.x).abs() <= HANDLE_GRAB;
+    let top = (pos.y - rect.min.y).abs() <= HANDLE_GRAB;
+    let bottom = !top && (pos.y - rect.max.y).abs() <= HANDLE_GRAB;
+    if left || right || top || bottom {
+        Some(DragMode::Resize { left, right, top, bottom })
+    }
+    else {
+        None
+    }
+}
+
+fn box_label(hitbox: &HitBox, id: BoxId) -> String {
+    format!("{} #{} (group {})", BoxType::name_of(hitbox.kind), id.index, id.group)
+}
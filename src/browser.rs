@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use arcsys::ggst::jonbin::GGSTJonBin;
+use eframe::egui::{self, Align, CollapsingHeader, Key, Modifiers, ScrollArea};
+
+use crate::boxes::BoxType;
+use crate::document::Document;
+
+// The searchable list of jonbins on the left of the editor.
+#[derive(Default)]
+pub struct JonbinBrowser {
+    search: String,
+    focus_search: bool,
+}
+
+impl JonbinBrowser {
+    // Returns the jonbin that was picked this frame, if any.
+    pub fn ui(&mut self, ui: &mut egui::Ui, doc: &Document, selected: &str) -> Option<String> {
+        let jonbins = &doc.jonbins;
+        if ui.input_mut().consume_key(Modifiers::COMMAND, Key::F) {
+            self.focus_search = true;
+        }
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            let response = ui.text_edit_singleline(&mut self.search);
+            if self.focus_search {
+                response.request_focus();
+                self.focus_search = false;
+            }
+        });
+        ui.label("Page Up/Down: previous/next frame, Ctrl+F: search");
+        ui.separator();
+
+        let names: Vec<&String> = jonbins.keys().filter(|name| fuzzy_match(&self.search, name)).collect();
+
+        // Only when nothing has keyboard focus, so the keys still work in text fields.
+        let mut switch_to = None;
+        if ui.memory().focus().is_none() {
+            let step = if ui.input_mut().consume_key(Modifiers::NONE, Key::PageDown) {
+                Some(1)
+            }
+            else if ui.input_mut().consume_key(Modifiers::NONE, Key::PageUp) {
+                Some(-1)
+            }
+            else {
+                None
+            };
+            if let (Some(step), false) = (step, names.is_empty()) {
+                let index = match names.iter().position(|name| *name == selected) {
+                    Some(index) => (index as isize + step).clamp(0, names.len() as isize - 1) as usize,
+                    None => 0,
+                };
+                switch_to = Some(names[index].clone());
+            }
+        }
+        let moved_to = switch_to.clone();
+
+        let mut groups: BTreeMap<&str, Vec<&String>> = BTreeMap::new();
+        for name in &names {
+            groups.entry(group_key(name)).or_default().push(name);
+        }
+        ScrollArea::vertical().show(ui, |ui| {
+            for (group, members) in &groups {
+                if members.len() == 1 {
+                    if let Some(name) = self.entry(ui, doc, members[0], selected, &moved_to) {
+                        switch_to = Some(name);
+                    }
+                    continue;
+                }
+                let open = if !self.search.is_empty() || moved_to.as_deref().map_or(false, |name| group_key(name) == *group) {
+                    Some(true)
+                }
+                else {
+                    None
+                };
+                CollapsingHeader::new(format!("{} ({})", group, members.len()))
+                .id_source(group)
+                .open(open)
+                .show(ui, |ui| {
+                    for name in members {
+                        if let Some(name) = self.entry(ui, doc, name, selected, &moved_to) {
+                            switch_to = Some(name);
+                        }
+                    }
+                });
+            }
+        });
+        switch_to
+    }
+
+    fn entry(&self, ui: &mut egui::Ui, doc: &Document, name: &str, selected: &str, moved_to: &Option<String>) -> Option<String> {
+        let jonb = &doc.jonbins[name];
+        let count: usize = jonb.boxes.iter().map(|group| group.len()).sum();
+        let marker = if doc.is_jonb_dirty(name) { "*" } else { "" };
+        let response = ui.selectable_label(name == selected, format!("{}{} ({})", name, marker, count))
+            .on_hover_text(box_counts(jonb));
+        if moved_to.as_deref() == Some(name) {
+            response.scroll_to_me(Some(Align::Center));
+        }
+        if response.clicked() {
+            return Some(name.to_string());
+        }
+        None
+    }
+}
+
+// Every character of the search has to appear in the name in order, ignoring case.
+fn fuzzy_match(search: &str, name: &str) -> bool {
+    let mut chars = name.chars().flat_map(|c| c.to_lowercase());
+    search.chars().flat_map(|c| c.to_lowercase()).all(|s| chars.any(|c| c == s))
+}
+
+// Frames of a move share a name apart from their trailing number, like `NmlAtk5A_00` and `NmlAtk5A_01`.
+pub fn group_key(name: &str) -> &str {
+    let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let trimmed = trimmed.strip_suffix('_').unwrap_or(trimmed);
+    if trimmed.is_empty() || trimmed.len() == name.len() {
+        name
+    }
+    else {
+        trimmed
+    }
+}
+
+fn box_counts(jonb: &GGSTJonBin) -> String {
+    let mut lines = Vec::new();
+    for box_type in BoxType::ALL {
+        let count = jonb.boxes.iter().flatten().filter(|hitbox| hitbox.kind == box_type as u32).count();
+        if count > 0 {
+            lines.push(format!("{}: {}", box_type.name(), count));
+        }
+    }
+    let unknown = jonb.boxes.iter().flatten().filter(|hitbox| BoxType::try_from(hitbox.kind).is_err()).count();
+    if unknown > 0 {
+        lines.push(format!("Unknown: {}", unknown));
+    }
+    if lines.is_empty() {
+        return "No boxes".to_string();
+    }
+    lines.join("\n")
+}
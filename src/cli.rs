@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Result as AResult};
+use arcsys::ggst::jonbin::GGSTJonBin;
+use structopt::StructOpt;
+
+use crate::boxes::{BoxesWindow, BoxType};
+use crate::diff::PacDiff;
+use crate::document::{Document, unpack_pac};
+use crate::open::open_file;
+
+#[derive(StructOpt)]
+#[structopt(name = "ggst_collision_editor_rs")]
+/// Collision editor for Team Red Arc System Works games.
+/// Run without a subcommand to open the editor window.
+pub struct Opt {
+    /// Parse jonbins in the Granblue Fantasy Versus format
+    #[structopt(long)]
+    pub gbvs: bool,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Extract every entry of a PAC into a directory
+    Unpack {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out_dir: PathBuf,
+    },
+    /// Build a PAC from a directory created by `unpack`
+    Repack {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// List the jonbins in a PAC
+    List {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+    },
+    /// Print the image names and boxes of one or all jonbins in a PAC
+    Dump {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        jonbin: Option<String>,
+    },
+    /// Report the jonbins and boxes that differ between two PACs
+    Diff {
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+    },
+    /// Reapply the edits made from `base` to `ours` onto `theirs`, an updated version of the PAC.
+    /// Conflicting changes keep the version from `theirs` and are listed.
+    Merge {
+        #[structopt(parse(from_os_str))]
+        base: PathBuf,
+        #[structopt(parse(from_os_str))]
+        ours: PathBuf,
+        #[structopt(parse(from_os_str))]
+        theirs: PathBuf,
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Write the image names and boxes of every jonbin as JSON, or YAML if the output ends in .yaml
+    Export {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Apply collision data written by `export` to a PAC
+    Import {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        #[structopt(parse(from_os_str))]
+        data: PathBuf,
+        /// Where to write the edited PAC. Overwrites the input if omitted.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Change the rect of a single box and save the PAC
+    SetBox {
+        #[structopt(parse(from_os_str))]
+        pac: PathBuf,
+        jonbin: String,
+        /// Index of the box group inside the jonbin
+        group: usize,
+        /// Index of the box inside the group
+        index: usize,
+        #[structopt(long)]
+        x: Option<f32>,
+        #[structopt(long)]
+        y: Option<f32>,
+        #[structopt(long)]
+        w: Option<f32>,
+        #[structopt(long)]
+        h: Option<f32>,
+        /// Where to write the edited PAC. Overwrites the input if omitted.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+pub fn run(command: Command, is_gbvs: bool) -> AResult<()> {
+    let mut window = BoxesWindow::default();
+    window.is_gbvs = is_gbvs;
+
+    match command {
+        Command::Unpack { pac, out_dir } => {
+            let file = open_file(&pac).map_err(|e| anyhow!("Failed to open {}: {}", pac.display(), e))?;
+            unpack_pac(&out_dir, file)?;
+        }
+        Command::Repack { dir, out } => {
+            window.open_dir(&dir)?;
+            window.write_pac(&out)?;
+        }
+        Command::List { pac } => {
+            window.open_file(&pac)?;
+            for (name, jonb) in &window.doc.jonbins {
+                let count: usize = jonb.boxes.iter().map(|group| group.len()).sum();
+                println!("{}\t{} images\t{} boxes", name, jonb.names.len(), count);
+            }
+        }
+        Command::Dump { pac, jonbin } => {
+            window.open_file(&pac)?;
+            match jonbin {
+                Some(name) => {
+                    let jonb = window.doc.jonbins.get(&name)
+                        .ok_or_else(|| anyhow!("No jonbin named {}!", name))?;
+                    dump_jonb(&name, jonb);
+                }
+                None => {
+                    for (name, jonb) in &window.doc.jonbins {
+                        dump_jonb(name, jonb);
+                    }
+                }
+            }
+        }
+        Command::Diff { old, new } => {
+            let old = Document::open(&old, is_gbvs)?;
+            let new = Document::open(&new, is_gbvs)?;
+            print!("{}", PacDiff::new(&old.jonbins, &new.jonbins));
+        }
+        Command::Merge { base, ours, theirs, output } => {
+            window.open_file(&ours)?;
+            let mut merged = window.merge_onto(&base, &theirs)?;
+            for conflict in merged.conflicts() {
+                println!("Conflict: {}", conflict);
+            }
+            merged.write_pac(&output)?;
+        }
+        Command::Export { pac, out } => {
+            window.open_file(&pac)?;
+            window.export_collision(&out)?;
+        }
+        Command::Import { pac, data, output } => {
+            window.open_file(&pac)?;
+            window.import_collision(&data)?;
+            window.write_pac(&output.unwrap_or(pac))?;
+        }
+        Command::SetBox { pac, jonbin, group, index, x, y, w, h, output } => {
+            window.open_file(&pac)?;
+            let hitbox = window.doc.jonbins.get_mut(&jonbin)
+                .ok_or_else(|| anyhow!("No jonbin named {}!", jonbin))?
+                .boxes.get_mut(group)
+                .and_then(|group| group.get_mut(index))
+                .ok_or_else(|| anyhow!("{} has no box #{} in group {}!", jonbin, index, group))?;
+            if let Some(x) = x { hitbox.rect.x_offset = x; }
+            if let Some(y) = y { hitbox.rect.y_offset = y; }
+            if let Some(w) = w { hitbox.rect.width = w; }
+            if let Some(h) = h { hitbox.rect.height = h; }
+            window.write_pac(&output.unwrap_or(pac))?;
+        }
+    }
+    Ok(())
+}
+
+fn dump_jonb(name: &str, jonb: &GGSTJonBin) {
+    println!("{}", name);
+    for (index, image) in jonb.names.iter().enumerate() {
+        println!("  image #{}: {}", index, image);
+    }
+    for (group, boxgroup) in jonb.boxes.iter().enumerate() {
+        for (index, hitbox) in boxgroup.iter().enumerate() {
+            println!("  [{}:{}] {} x={} y={} w={} h={}",
+                group, index, BoxType::name_of(hitbox.kind),
+                hitbox.rect.x_offset, hitbox.rect.y_offset,
+                hitbox.rect.width, hitbox.rect.height);
+        }
+    }
+}
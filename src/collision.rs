@@ -0,0 +1,192 @@
+use std::{collections::BTreeMap, fs::File, io::{BufReader, BufWriter}, path::Path};
+use arcsys::ggst::jonbin::{GGSTJonBin, HitBox, Rect};
+use serde::{Serialize, Deserialize};
+use anyhow::{anyhow, Context, Result as AResult};
+
+use crate::boxes::BoxType;
+
+// The editable part of every jonbin in a PAC, in a form that reads well in a diff.
+#[derive(Serialize, Deserialize)]
+pub struct CollisionData {
+    pub jonbins: BTreeMap<String, JonbinData>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+pub struct JonbinData {
+    pub names: Vec<String>,
+    pub boxes: Vec<Vec<BoxData>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+pub struct BoxData {
+    pub kind: u32,
+    // Only written for readability, the kind is what gets imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind_name: Option<String>,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    // GBVS boxes only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<u32>,
+}
+
+enum Format {
+    Json,
+    Yaml,
+}
+
+fn format_of(path: &Path) -> Format {
+    match path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase()) {
+        Some(ext) if ext == "yaml" || ext == "yml" => Format::Yaml,
+        _ => Format::Json,
+    }
+}
+
+impl CollisionData {
+    pub fn from_jonbins(jonbins: &BTreeMap<String, GGSTJonBin>) -> Self {
+        Self {
+            jonbins: jonbins.iter().map(|(name, jonb)| (name.clone(), JonbinData::from_jonb(jonb))).collect(),
+        }
+    }
+
+    pub fn read(path: &Path) -> AResult<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let reader = BufReader::new(file);
+        let data = match format_of(path) {
+            Format::Json => serde_json::from_reader(reader)?,
+            Format::Yaml => serde_yaml::from_reader(reader)?,
+        };
+        Ok(data)
+    }
+
+    pub fn write(&self, path: &Path) -> AResult<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        match format_of(path) {
+            Format::Json => serde_json::to_writer_pretty(writer, self)?,
+            Format::Yaml => serde_yaml::to_writer(writer, self)?,
+        }
+        Ok(())
+    }
+
+    // Fails without changing anything if the data names a jonbin the PAC doesn't have, or doesn't
+    // keep every box in the group of its kind with the same groups as the jonbin it replaces.
+    pub fn check(&self, jonbins: &BTreeMap<String, GGSTJonBin>) -> AResult<()> {
+        let group_counts = jonbins.iter().map(|(name, jonb)| (name.clone(), jonb.boxes.len())).collect();
+        self.check_groups(&group_counts)
+    }
+
+    // `check` against the number of box groups of each jonbin in the PAC.
+    fn check_groups(&self, group_counts: &BTreeMap<String, usize>) -> AResult<()> {
+        let missing: Vec<&str> = self.jonbins.keys()
+            .filter(|name| !group_counts.contains_key(*name))
+            .map(|name| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!("These jonbins are not in the PAC: {}", missing.join(", ")));
+        }
+        let mut problems = Vec::new();
+        for (name, data) in &self.jonbins {
+            let groups = group_counts[name];
+            if data.boxes.len() != groups {
+                problems.push(format!("{} has {} box groups instead of {}", name, data.boxes.len(), groups));
+                continue;
+            }
+            for (group, boxgroup) in data.boxes.iter().enumerate() {
+                for (index, box_data) in boxgroup.iter().enumerate() {
+                    if box_data.kind as usize != group {
+                        problems.push(format!("{} box {}:{} has kind {} in group {}", name, group, index, box_data.kind, group));
+                    }
+                }
+            }
+        }
+        if !problems.is_empty() {
+            return Err(anyhow!("The collision data doesn't match the PAC:\n{}", problems.join("\n")));
+        }
+        Ok(())
+    }
+}
+
+impl JonbinData {
+    pub fn from_jonb(jonb: &GGSTJonBin) -> Self {
+        Self {
+            names: jonb.names.clone(),
+            boxes: jonb.boxes.iter().map(|group| group.iter().map(BoxData::from_hitbox).collect()).collect(),
+        }
+    }
+
+    // GBVS boxes without an extra value in the data get 0, like a newly added box.
+    pub fn apply(&self, jonb: &mut GGSTJonBin, is_gbvs: bool) {
+        let boxes = self.boxes.iter().map(|boxgroup| {
+            boxgroup.iter().map(|data| {
+                HitBox {
+                    kind: data.kind,
+                    rect: Rect { x_offset: data.x, y_offset: data.y, width: data.w, height: data.h },
+                    extra: if is_gbvs { Some(data.extra.unwrap_or(0)) } else { None },
+                }
+            }).collect()
+        }).collect();
+        jonb.names = self.names.clone();
+        jonb.boxes = boxes;
+    }
+}
+
+impl BoxData {
+    pub fn from_hitbox(hitbox: &HitBox) -> Self {
+        Self {
+            kind: hitbox.kind,
+            kind_name: Some(BoxType::name_of(hitbox.kind).to_string()),
+            x: hitbox.rect.x_offset,
+            y: hitbox.rect.y_offset,
+            w: hitbox.rect.width,
+            h: hitbox.rect.height,
+            extra: hitbox.extra,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn box_data(kind: u32) -> BoxData {
+        BoxData { kind, kind_name: None, x: 0.0, y: 0.0, w: 10.0, h: 10.0, extra: None }
+    }
+
+    fn data(boxes: Vec<Vec<BoxData>>) -> CollisionData {
+        let jonb = JonbinData { names: vec!["image".to_string()], boxes };
+        CollisionData { jonbins: [("nmlatk5a".to_string(), jonb)].into_iter().collect() }
+    }
+
+    // The PAC's nmlatk5a has two box groups, for hurtboxes and hitboxes.
+    fn group_counts() -> BTreeMap<String, usize> {
+        [("nmlatk5a".to_string(), 2)].into_iter().collect()
+    }
+
+    #[test]
+    fn accepts_matching_data() {
+        let data = data(vec![vec![box_data(0), box_data(0)], vec![box_data(1)]]);
+        assert!(data.check_groups(&group_counts()).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_jonbins() {
+        let mut data = data(vec![vec![], vec![]]);
+        let jonb = data.jonbins.remove("nmlatk5a").unwrap();
+        data.jonbins.insert("nmlatk2a".to_string(), jonb);
+        assert!(data.check_groups(&group_counts()).is_err());
+    }
+
+    #[test]
+    fn rejects_a_different_group_count() {
+        let data = data(vec![vec![box_data(0)], vec![], vec![]]);
+        assert!(data.check_groups(&group_counts()).is_err());
+    }
+
+    #[test]
+    fn rejects_boxes_in_the_wrong_group() {
+        let data = data(vec![vec![box_data(0)], vec![box_data(0)]]);
+        assert!(data.check_groups(&group_counts()).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::{collections::BTreeMap, fmt};
+use arcsys::ggst::jonbin::{GGSTJonBin, HitBox};
+
+use crate::boxes::{BoxId, BoxType};
+
+pub enum BoxChange {
+    Added(HitBox),
+    Removed(HitBox),
+    Changed { old: HitBox, new: HitBox },
+}
+
+pub struct BoxDiff {
+    pub id: BoxId,
+    pub change: BoxChange,
+}
+
+pub struct JonbinDiff {
+    pub name: String,
+    // The old and new image names, if they differ.
+    pub names: Option<(Vec<String>, Vec<String>)>,
+    pub boxes: Vec<BoxDiff>,
+}
+
+#[derive(Default)]
+pub struct PacDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<JonbinDiff>,
+}
+
+impl PacDiff {
+    pub fn new(old: &BTreeMap<String, GGSTJonBin>, new: &BTreeMap<String, GGSTJonBin>) -> Self {
+        let mut diff = Self::default();
+        for (name, old_jonb) in old {
+            match new.get(name) {
+                Some(new_jonb) => {
+                    if let Some(changed) = diff_jonb(name, old_jonb, new_jonb) {
+                        diff.changed.push(changed);
+                    }
+                },
+                None => diff.removed.push(name.clone()),
+            }
+        }
+        diff.added = new.keys().filter(|name| !old.contains_key(*name)).cloned().collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+// Boxes are matched by their group and index, like everywhere else in the editor.
+pub fn diff_jonb(name: &str, old: &GGSTJonBin, new: &GGSTJonBin) -> Option<JonbinDiff> {
+    let mut boxes = Vec::new();
+    for group in 0..old.boxes.len().max(new.boxes.len()) {
+        let old_group = old.boxes.get(group).map(|group| group.as_slice()).unwrap_or(&[]);
+        let new_group = new.boxes.get(group).map(|group| group.as_slice()).unwrap_or(&[]);
+        for index in 0..old_group.len().max(new_group.len()) {
+            let id = BoxId { group, index };
+            let change = match (old_group.get(index), new_group.get(index)) {
+                (Some(old), Some(new)) if !same_box(old, new) => BoxChange::Changed { old: *old, new: *new },
+                (Some(old), None) => BoxChange::Removed(*old),
+                (None, Some(new)) => BoxChange::Added(*new),
+                _ => continue,
+            };
+            boxes.push(BoxDiff { id, change });
+        }
+    }
+    let names = if old.names != new.names {
+        Some((old.names.clone(), new.names.clone()))
+    }
+    else {
+        None
+    };
+    if boxes.is_empty() && names.is_none() {
+        return None;
+    }
+    Some(JonbinDiff { name: name.to_string(), names, boxes })
+}
+
+// The one definition of an unchanged box, used by the diff, the merge and dirty tracking.
+// GBVS boxes also carry an extra value, which counts as a change too.
+pub fn same_box(a: &HitBox, b: &HitBox) -> bool {
+    a.kind == b.kind
+        && a.rect.x_offset == b.rect.x_offset
+        && a.rect.y_offset == b.rect.y_offset
+        && a.rect.width == b.rect.width
+        && a.rect.height == b.rect.height
+        && a.extra == b.extra
+}
+
+pub fn same_jonb(a: &GGSTJonBin, b: &GGSTJonBin) -> bool {
+    a.names == b.names
+        && a.boxes.len() == b.boxes.len()
+        && a.boxes.iter().zip(&b.boxes).all(|(a, b)| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_box(a, b))
+        })
+}
+
+impl fmt::Display for BoxDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}:{}] ", self.id.group, self.id.index)?;
+        match &self.change {
+            BoxChange::Added(new) => write!(f, "+ {} x={} y={} w={} h={}",
+                BoxType::name_of(new.kind), new.rect.x_offset, new.rect.y_offset, new.rect.width, new.rect.height),
+            BoxChange::Removed(old) => write!(f, "- {} x={} y={} w={} h={}",
+                BoxType::name_of(old.kind), old.rect.x_offset, old.rect.y_offset, old.rect.width, old.rect.height),
+            BoxChange::Changed { old, new } => {
+                write!(f, "~ {}", BoxType::name_of(new.kind))?;
+                if old.kind != new.kind {
+                    write!(f, " (was {})", BoxType::name_of(old.kind))?;
+                }
+                write!(f, " dx={} dy={} dw={} dh={}",
+                    new.rect.x_offset - old.rect.x_offset, new.rect.y_offset - old.rect.y_offset,
+                    new.rect.width - old.rect.width, new.rect.height - old.rect.height)?;
+                match (old.extra, new.extra) {
+                    (Some(old_extra), Some(new_extra)) if old_extra != new_extra => write!(f, " extra {} -> {}", old_extra, new_extra),
+                    _ => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for JonbinDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "~ {}", self.name)?;
+        if let Some((old, new)) = &self.names {
+            writeln!(f, "  images: {:?} -> {:?}", old, new)?;
+        }
+        for box_diff in &self.boxes {
+            writeln!(f, "  {}", box_diff)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PacDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No collision changes.");
+        }
+        for name in &self.added {
+            writeln!(f, "+ {}", name)?;
+        }
+        for name in &self.removed {
+            writeln!(f, "- {}", name)?;
+        }
+        for jonb_diff in &self.changed {
+            write!(f, "{}", jonb_diff)?;
+        }
+        Ok(())
+    }
+}
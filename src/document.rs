@@ -0,0 +1,348 @@
+use std::{collections::{BTreeMap, BTreeSet}, fs::{self, File, create_dir_all}, io::{Write, Read, BufReader}, path::{Path, PathBuf}};
+use arcsys::ggst::{pac::{GGSTPac, GGSTPacEntry}, jonbin::GGSTJonBin};
+use serde::{Serialize, Deserialize};
+use anyhow::{anyhow, Context, Result as AResult};
+
+use crate::boxes::clone_jonb;
+use crate::diff::same_jonb;
+use crate::open::open_file;
+
+#[derive(Serialize, Deserialize)]
+enum MetaKind {
+    Pac(GGSTPac),
+}
+
+#[derive(Default)]
+pub struct Document {
+    pac: Option<GGSTPac>,
+    pub jonbins: BTreeMap<String, GGSTJonBin>,
+    // Names of the PAC entries that parsed as jonbins, so entries whose jonbin has been
+    // removed from `jonbins` can be dropped when the PAC is rebuilt.
+    jonbin_entries: BTreeSet<String>,
+    // The jonbins as they were when the PAC was opened, to compare edits against.
+    originals: BTreeMap<String, GGSTJonBin>,
+    // The jonbins as they were last opened or saved, and the names of the ones that differ from that.
+    saved: BTreeMap<String, GGSTJonBin>,
+    dirty: BTreeSet<String>,
+}
+
+impl Document {
+    pub fn open(path: &PathBuf, is_gbvs: bool) -> AResult<Self> {
+        let pac = open_file(path).map_err(|e| anyhow!("Failed to open {}: {}
+Make sure that your file is a valid Team Red format PAC.
+PACs rebuilt using the GeoArcSys tools are not compatible!", path.display(), e))?;
+        Ok(Self::from_pac(pac, is_gbvs))
+    }
+
+    // Reads a directory written by `unpack_pac`. Files that aren't listed in meta.json are added as new entries.
+    pub fn open_dir(dir: &Path, is_gbvs: bool) -> AResult<Self> {
+        let meta_file = File::open(dir.join("meta.json"))
+            .with_context(|| format!("{} is not an unpacked PAC directory!", dir.display()))?;
+        let meta: MetaKind = serde_json::from_reader(BufReader::new(meta_file))?;
+        let MetaKind::Pac(mut pac) = meta;
+
+        pac.files = pac
+        .files
+        .into_iter()
+        .filter_map(|mut entry| {
+            let mut contents = Vec::new();
+            if File::open(dir.join(&entry.name))
+                .and_then(|mut f| f.read_to_end(&mut contents))
+                .is_ok()
+            {
+                entry.contents = contents;
+                Some(entry)
+            } else {
+                println!("Failed to read {}! Excluding from PAC file", entry.name);
+                None
+            }
+        })
+        .collect::<Vec<GGSTPacEntry>>();
+
+        for path in fs::read_dir(dir)? {
+            let file = path?.path();
+            let name = match file.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            if !file.is_file() || name == "meta.json" || pac.files.iter().any(|entry| entry.name == name) {
+                continue;
+            }
+            let mut contents = Vec::new();
+            if let Err(e) = File::open(&file).and_then(|mut f| f.read_to_end(&mut contents)) {
+                println!("Error reading file {}: {}", file.display(), e);
+                continue;
+            };
+            pac.files.push(GGSTPacEntry {
+                unknown: hash_names(&name),
+                id: 0,
+                name,
+                contents,
+            });
+        }
+        sort_entries(&mut pac.files);
+
+        Ok(Self::from_pac(pac, is_gbvs))
+    }
+
+    pub fn from_pac(pac: GGSTPac, is_gbvs: bool) -> Self {
+        let mut jonbins = BTreeMap::new();
+        for entry in &pac.files {
+            match GGSTJonBin::parse(&entry.contents, is_gbvs) {
+                Ok(jonb) => {
+                    jonbins.insert(entry_key(&entry.name), jonb);
+                },
+                Err(e) => {
+                    println!("Error reading file {}: {}", entry.name, e);
+                },
+            }
+        }
+        Self {
+            pac: Some(pac),
+            jonbin_entries: jonbins.keys().cloned().collect(),
+            originals: jonbins.iter().map(|(name, jonb)| (name.clone(), clone_jonb(jonb))).collect(),
+            saved: jonbins.iter().map(|(name, jonb)| (name.clone(), clone_jonb(jonb))).collect(),
+            dirty: BTreeSet::new(),
+            jonbins,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.pac.is_some()
+    }
+
+    pub fn original(&self, name: &str) -> Option<&GGSTJonBin> {
+        self.originals.get(name)
+    }
+
+    // Compares one jonbin against the saved version, covering edits that were undone back to it.
+    pub fn update_dirty(&mut self, name: &str) {
+        let changed = match (self.jonbins.get(name), self.saved.get(name)) {
+            (Some(jonb), Some(saved)) => !same_jonb(jonb, saved),
+            (None, None) => false,
+            _ => true,
+        };
+        if changed {
+            self.dirty.insert(name.to_string());
+        }
+        else {
+            self.dirty.remove(name);
+        }
+    }
+
+    pub fn refresh_dirty(&mut self) {
+        let names: BTreeSet<String> = self.jonbins.keys().chain(self.saved.keys()).cloned().collect();
+        for name in names {
+            self.update_dirty(&name);
+        }
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = self.jonbins.iter().map(|(name, jonb)| (name.clone(), clone_jonb(jonb))).collect();
+        self.dirty.clear();
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    pub fn is_jonb_dirty(&self, name: &str) -> bool {
+        self.dirty.contains(name)
+    }
+
+    pub fn dirty_names(&self) -> impl Iterator<Item = &String> {
+        self.dirty.iter()
+    }
+
+    // A name is taken by a jonbin, or by a PAC entry that isn't a jonbin and would be overwritten when saving.
+    pub fn name_taken(&self, name: &str) -> bool {
+        if self.jonbins.contains_key(name) {
+            return true;
+        }
+        self.pac.as_ref().map_or(false, |pac| other_entry_named(&pac.files, &self.jonbin_entries, name))
+    }
+
+    // Moves a jonbin to a new name, renaming its PAC entry and rehashing it so the entry order stays valid.
+    pub fn rename_jonb(&mut self, old_name: &str, new_name: &str) -> bool {
+        if self.name_taken(new_name) || !self.jonbins.contains_key(old_name) {
+            return false;
+        }
+        let jonb = self.jonbins.remove(old_name).unwrap();
+        self.jonbins.insert(new_name.to_string(), jonb);
+        if let Some(pac) = self.pac.as_mut() {
+            rename_entry(&mut pac.files, &mut self.jonbin_entries, old_name, new_name);
+        }
+        true
+    }
+
+    // Writes every jonbin back into its PAC entry, registering new jonbins as new entries.
+    fn sync(&mut self) -> AResult<&GGSTPac> {
+        let pac = self.pac.as_mut().ok_or_else(|| anyhow!("No PAC is open!"))?;
+        let jonbins = &self.jonbins;
+
+        drop_removed_entries(&mut pac.files, jonbins, &self.jonbin_entries);
+        for entry in &mut pac.files {
+            if let Some(jonb) = jonbins.get(&entry_key(&entry.name)) {
+                entry.contents = GGSTJonBin::to_bytes(jonb);
+            }
+        }
+        for (name, jonb) in jonbins {
+            if !pac.files.iter().any(|entry| entry_key(&entry.name) == *name) {
+                pac.files.push(GGSTPacEntry {
+                    unknown: hash_names(name),
+                    id: 0,
+                    name: name.clone(),
+                    contents: GGSTJonBin::to_bytes(jonb),
+                });
+            }
+        }
+        sort_entries(&mut pac.files);
+        self.jonbin_entries = jonbins.keys().cloned().collect();
+        Ok(pac)
+    }
+
+    pub fn to_bytes(&mut self) -> AResult<Vec<u8>> {
+        Ok(self.sync()?.to_bytes())
+    }
+}
+
+// Jonbins are keyed by their entry name without extension.
+fn entry_key(name: &str) -> String {
+    match Path::new(name).file_stem().and_then(|stem| stem.to_str()) {
+        Some(stem) => stem.to_string(),
+        None => name.to_string(),
+    }
+}
+
+// Whether `name` belongs to an entry that isn't one of the jonbins.
+fn other_entry_named(files: &[GGSTPacEntry], jonbin_entries: &BTreeSet<String>, name: &str) -> bool {
+    files.iter().any(|entry| {
+        let key = entry_key(&entry.name);
+        key == name && !jonbin_entries.contains(&key)
+    })
+}
+
+// Renames a jonbin's entry, keeping its extension, and rehashes it so the entry order stays valid.
+fn rename_entry(files: &mut Vec<GGSTPacEntry>, jonbin_entries: &mut BTreeSet<String>, old_name: &str, new_name: &str) {
+    // A jonbin that was removed but not saved yet still has its entry, which would end up
+    // as a second entry with the new name.
+    if jonbin_entries.remove(new_name) {
+        files.retain(|entry| entry_key(&entry.name) != new_name);
+    }
+    if jonbin_entries.remove(old_name) {
+        jonbin_entries.insert(new_name.to_string());
+    }
+    if let Some(entry) = files.iter_mut().find(|entry| entry_key(&entry.name) == old_name) {
+        entry.name = match Path::new(&entry.name).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) => format!("{}.{}", new_name, ext),
+            None => new_name.to_string(),
+        };
+        entry.unknown = hash_names(&entry.name);
+    }
+    sort_entries(files);
+}
+
+// Drops the entries of jonbins that have been removed since the PAC was opened or saved.
+fn drop_removed_entries<T>(files: &mut Vec<GGSTPacEntry>, jonbins: &BTreeMap<String, T>, jonbin_entries: &BTreeSet<String>) {
+    files.retain(|entry| {
+        let key = entry_key(&entry.name);
+        jonbins.contains_key(&key) || !jonbin_entries.contains(&key)
+    });
+}
+
+fn sort_entries(files: &mut [GGSTPacEntry]) {
+    files.sort_by(|a, b| a.unknown.cmp(&b.unknown));
+    let mut index: u32 = 0;
+    for entry in files.iter_mut() {
+        entry.id = index;
+        index += 1;
+    }
+}
+
+pub fn hash_names(name: &str) -> u32
+{
+    let mut new_name = "".to_string();
+    for name_char in name.as_bytes() {
+        new_name.push((*name_char as char).to_ascii_lowercase());
+    }
+    let mut result: u32 = 0;
+    for hash_char in new_name.as_bytes() {
+        result = (*hash_char as u32).wrapping_add(137_u32.wrapping_mul(result));
+    };
+    result
+}
+
+pub fn unpack_pac(dir: &Path, pac: GGSTPac) -> AResult<()> {
+    create_dir_all(dir)?;
+    for i in &pac.files {
+        File::create(dir.join(&i.name))?.write_all(&i.contents)?;
+    }
+
+    let meta_file = File::create(dir.join("meta.json"))?;
+    let mut serializer = serde_json::Serializer::new(meta_file);
+
+    let meta = MetaKind::Pac(pac);
+    meta.serialize(&mut serializer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> GGSTPacEntry {
+        GGSTPacEntry { unknown: hash_names(name), id: 0, name: name.to_string(), contents: Vec::new() }
+    }
+
+    // Two jonbins and a file that isn't one, in PAC order.
+    fn entries() -> (Vec<GGSTPacEntry>, BTreeSet<String>) {
+        let mut files = vec![entry("nmlatk5a.jonbin"), entry("nmlatk6a.jonbin"), entry("meta.bin")];
+        sort_entries(&mut files);
+        let jonbin_entries = ["nmlatk5a", "nmlatk6a"].iter().map(|name| name.to_string()).collect();
+        (files, jonbin_entries)
+    }
+
+    fn keys(files: &[GGSTPacEntry]) -> Vec<String> {
+        files.iter().map(|entry| entry_key(&entry.name)).collect()
+    }
+
+    #[test]
+    fn rename_rehashes_and_keeps_order() {
+        let (mut files, mut jonbin_entries) = entries();
+        rename_entry(&mut files, &mut jonbin_entries, "nmlatk5a", "cmnact00");
+        let renamed = files.iter().find(|entry| entry.name == "cmnact00.jonbin").unwrap();
+        assert_eq!(renamed.unknown, hash_names("cmnact00.jonbin"));
+        assert!(files.windows(2).all(|pair| pair[0].unknown <= pair[1].unknown));
+        assert!(files.iter().enumerate().all(|(index, entry)| entry.id == index as u32));
+        assert!(jonbin_entries.contains("cmnact00") && !jonbin_entries.contains("nmlatk5a"));
+    }
+
+    #[test]
+    fn rename_onto_removed_jonbin_leaves_one_entry() {
+        let (mut files, mut jonbin_entries) = entries();
+        // nmlatk5a was removed, then nmlatk6a renamed to it before saving.
+        rename_entry(&mut files, &mut jonbin_entries, "nmlatk6a", "nmlatk5a");
+        let jonbins: BTreeMap<String, ()> = [("nmlatk5a".to_string(), ())].into_iter().collect();
+        drop_removed_entries(&mut files, &jonbins, &jonbin_entries);
+        assert_eq!(keys(&files).iter().filter(|key| *key == "nmlatk5a").count(), 1);
+        assert!(!keys(&files).contains(&"nmlatk6a".to_string()));
+    }
+
+    #[test]
+    fn other_entry_names_are_taken() {
+        let (files, jonbin_entries) = entries();
+        assert!(other_entry_named(&files, &jonbin_entries, "meta"));
+        assert!(!other_entry_named(&files, &jonbin_entries, "nmlatk5a"));
+        assert!(!other_entry_named(&files, &jonbin_entries, "nmlatk2a"));
+    }
+
+    #[test]
+    fn removed_jonbins_lose_their_entry() {
+        let (mut files, jonbin_entries) = entries();
+        let jonbins: BTreeMap<String, ()> = [("nmlatk6a".to_string(), ())].into_iter().collect();
+        drop_removed_entries(&mut files, &jonbins, &jonbin_entries);
+        let mut remaining = keys(&files);
+        remaining.sort();
+        assert_eq!(remaining, vec!["meta".to_string(), "nmlatk6a".to_string()]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+use arcsys::ggst::jonbin::GGSTJonBin;
+
+// Consecutive edits with the same merge key that land within this window become one undo step,
+// so typing a number into a box field doesn't take one undo per keystroke.
+const MERGE_WINDOW: Duration = Duration::from_millis(1000);
+
+pub struct Command {
+    pub name: String,
+    pub before: Option<GGSTJonBin>,
+    pub after: Option<GGSTJonBin>,
+    pub selected_before: String,
+    pub selected_after: String,
+    // Set when the command renamed a jonbin from this name to `name`.
+    pub renamed_from: Option<String>,
+    merge_key: Option<String>,
+    time: Instant,
+}
+
+impl Command {
+    pub fn new(name: &str, before: Option<GGSTJonBin>, after: Option<GGSTJonBin>, selected_before: &str, selected_after: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            before,
+            after,
+            selected_before: selected_before.to_string(),
+            selected_after: selected_after.to_string(),
+            renamed_from: None,
+            merge_key: None,
+            time: Instant::now(),
+        }
+    }
+
+    pub fn renamed_from(mut self, old_name: &str) -> Self {
+        self.renamed_from = Some(old_name.to_string());
+        self
+    }
+
+    pub fn merge_key(mut self, key: String) -> Self {
+        self.merge_key = Some(key);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+}
+
+impl History {
+    pub fn push(&mut self, command: Command) {
+        self.redo.clear();
+        if let Some(last) = self.undo.last_mut() {
+            if last.merge_key.is_some()
+                && last.merge_key == command.merge_key
+                && command.time.duration_since(last.time) < MERGE_WINDOW
+            {
+                last.after = command.after;
+                last.selected_after = command.selected_after;
+                last.time = command.time;
+                return;
+            }
+        }
+        self.undo.push(command);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Command> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Command> {
+        self.redo.pop()
+    }
+
+    pub fn push_undone(&mut self, command: Command) {
+        self.redo.push(command);
+    }
+
+    pub fn push_redone(&mut self, command: Command) {
+        self.undo.push(command);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
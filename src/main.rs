@@ -2,14 +2,40 @@
 
 mod open;
 mod boxes;
+mod document;
+mod cli;
+mod history;
+mod sprites;
+mod collision;
+mod diff;
+mod merge;
+mod browser;
+mod timeline;
+mod save;
+mod recovery;
+mod settings;
 
 use anyhow::Result as AResult;
 
-use boxes::BoxesWindow;
+use arcsys::ggst::jonbin::GGSTJonBin;
+use boxes::{BoxesWindow, BoxType, clone_jonb};
+use sprites::SpriteCache;
 use eframe::{egui::{self}, emath::Vec2};
 use image::{DynamicImage};
+use recovery::{Orphans, Recovery, Session};
+use settings::Settings;
+use std::{path::{Path, PathBuf}, thread, time::{Duration, Instant}};
+use structopt::StructOpt;
+
+const TITLE: &str = "GGST Collision Editor Rust v3.4";
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
 
 fn main() -> AResult<()> {
+    let opt = cli::Opt::from_args();
+    if let Some(command) = opt.command {
+        return cli::run(command, opt.gbvs);
+    }
+
     let icon = match image::open("Strive.png") {
         Ok(icon) => icon.to_rgba8(),
         Err(_) => DynamicImage::new_rgba8(256, 256).to_rgba8(),
@@ -27,27 +53,113 @@ fn main() -> AResult<()> {
         ..Default::default()
     };
     eframe::run_native(
-        "GGST Collision Editor Rust v3.4",
+        TITLE,
         options,
-        Box::new(|_cc| Box::new(MyApp::default())),
+        Box::new(move |cc| {
+            let settings = settings::load();
+            cc.egui_ctx.set_visuals(if settings.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() });
+            let mut app = MyApp::default();
+            // `--gbvs` only applies to this run, the saved setting is changed under Settings.
+            app.is_gbvs = opt.gbvs || settings.is_gbvs;
+            if let Some(dir) = &settings.sprite_dir {
+                app.sprites.set_dir(dir.clone());
+            }
+            app.sprites.origin = Vec2::from(settings.sprite_origin);
+            app.settings = settings;
+            app.recovered = recovery::find_orphans();
+            app.recovery = Recovery::new();
+            if app.recovery.is_some() {
+                start_autosave_timer(cc.egui_ctx.clone());
+            }
+            Box::new(app)
+        }),
     );
 }
 
+// egui only draws a frame when there is input, so an idle editor is woken up to autosave. It wakes
+// up more often than the interval, so a save is never more than a few seconds late.
+fn start_autosave_timer(ctx: egui::Context) {
+    thread::spawn(move || loop {
+        thread::sleep(AUTOSAVE_INTERVAL / 6);
+        ctx.request_repaint();
+    });
+}
+
+struct Tab {
+    path: PathBuf,
+    // False for tabs that weren't opened from `path`, like merge results, which have to be saved somewhere new.
+    has_file: bool,
+    boxes_window: BoxesWindow,
+}
+
+impl Tab {
+    fn title(&self) -> String {
+        match self.path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    // The title with a `*` when there are unsaved changes.
+    fn label(&self) -> String {
+        if self.boxes_window.is_dirty() {
+            format!("{}*", self.title())
+        }
+        else {
+            self.title()
+        }
+    }
+}
+
+// What is waiting on the save/discard/cancel prompt.
+#[derive(Copy, Clone)]
+enum PendingClose {
+    Tab(usize),
+    Window,
+}
+
 #[derive(Default)]
 struct MyApp {
     dropped_files: Vec<egui::DroppedFile>,
-    picked_path: Option<String>,
-    success: bool,
-    boxes_window: BoxesWindow,
+    tabs: Vec<Tab>,
+    active: usize,
+    is_gbvs: bool,
+    sprites: SpriteCache,
+    // A jonbin copied from any of the open tabs, with the name it had there.
+    clipboard: Option<(String, GGSTJonBin)>,
+    error: Option<String>,
+    pending_close: Option<PendingClose>,
+    // Set once the user chose to discard their changes, so closing the window goes through.
+    allow_close: bool,
+    window_title: String,
+    // None when there is nowhere to write the recovery file.
+    recovery: Option<Recovery>,
+    // Left behind by editors that didn't close properly, waiting for the user to restore or discard them.
+    recovered: Option<Orphans>,
+    last_autosave: Option<Instant>,
+    settings: Settings,
 }
 
 impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn on_exit_event(&mut self) -> bool {
+        if self.allow_close || !self.tabs.iter().any(|tab| tab.boxes_window.is_dirty()) {
+            self.clear_recovery();
+            self.save_settings();
+            return true;
+        }
+        self.pending_close = Some(PendingClose::Window);
+        false
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.menu_button("File", |ui| {
                     self.file_menu(ui)
                 });
+                ui.menu_button("Edit", |ui| {
+                    self.edit_menu(ui)
+                });
                 ui.menu_button("Modify Boxes", |ui| {
                     self.modify_menu(ui)
                 });
@@ -56,45 +168,393 @@ impl eframe::App for MyApp {
                 });
                 let mut visuals = ui.ctx().style().visuals.clone();
                 visuals.light_dark_radio_buttons(ui);
+                self.settings.dark_mode = visuals.dark_mode;
                 ui.ctx().set_visuals(visuals);
             });    
 
             ui.label("Open from the File menu, or drag-and-drop the file here.");
 
-            if let Some(picked_path) = &self.picked_path {
-                if self.success == false{
-                    ui.horizontal(|ui| {
-                        ui.label("Failed to open file!
-Make sure that your file is a valid Team Red format PAC.
-PACs rebuilt using the GeoArcSys tools are not compatible!
-                        ");
-                    });        
-                }
-                else {
-                    ui.horizontal(|ui| {
-                        ui.label("Picked file:");
-                        ui.monospace(picked_path);
-                    });
-                    self.boxes_window.ui(ui);
-                }
+            self.tab_bar(ui);
+            if self.active < self.tabs.len() {
+                // The other tabs are passed along so their jonbins can be overlaid.
+                let (before, rest) = self.tabs.split_at_mut(self.active);
+                let (tab, after) = rest.split_first_mut().unwrap();
+                let others: Vec<_> = before.iter().chain(after.iter())
+                    .map(|other| (other.title(), &other.boxes_window.doc.jonbins))
+                    .collect();
+                ui.horizontal(|ui| {
+                    ui.label("Picked file:");
+                    ui.monospace(tab.path.display().to_string());
+                });
+                tab.boxes_window.ui(ui, &mut self.sprites, &others);
             }
 
             // Show dropped files (if any):
             if !self.dropped_files.is_empty() {
-                for file in &self.dropped_files {
-                    let &path = &file.path.as_ref().unwrap();
-                    self.success = self.boxes_window.open_file(path);
-                    self.picked_path = Some(path.display().to_string());
+                for file in std::mem::take(&mut self.dropped_files) {
+                    if let Some(path) = &file.path {
+                        self.open(path);
+                    }
                 }
-                self.dropped_files.clear();
             }
         });
 
+        if !self.tabs.is_empty() {
+            if ctx.input_mut().consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::S) {
+                self.save_tab_as(self.active);
+            }
+            else if ctx.input_mut().consume_key(egui::Modifiers::COMMAND, egui::Key::S) {
+                self.save_tab(self.active);
+            }
+        }
+        self.close_prompt(ctx, frame);
+        self.recovery_prompt(ctx);
+        self.autosave();
+        self.error_dialog(ctx);
         self.detect_files_being_dropped(ctx);
+
+        let title = match self.tabs.get(self.active) {
+            Some(tab) => format!("{} - {}", tab.label(), TITLE),
+            None => TITLE.to_string(),
+        };
+        if title != self.window_title {
+            frame.set_window_title(&title);
+            self.window_title = title;
+        }
     }
 }
 
 impl MyApp {
+    fn open(&mut self, path: &PathBuf) {
+        if let Some(index) = self.tabs.iter().position(|tab| tab.has_file && tab.path == *path) {
+            self.active = index;
+            return;
+        }
+        let mut boxes_window = BoxesWindow::default();
+        boxes_window.is_gbvs = self.is_gbvs;
+        match boxes_window.open_file(path) {
+            Ok(_) => {
+                if let Some(view) = self.settings.view(path) {
+                    boxes_window.set_view(view);
+                }
+                self.tabs.push(Tab { path: path.clone(), has_file: true, boxes_window });
+                self.active = self.tabs.len() - 1;
+                self.settings.add_recent(path);
+                self.save_settings();
+            },
+            Err(e) => {
+                self.settings.remove_recent(path);
+                self.report(Err(e));
+            },
+        }
+    }
+
+    // Starts in the folder the last PAC was opened from.
+    fn pac_dialog(&self) -> rfd::FileDialog {
+        let dialog = rfd::FileDialog::new().add_filter("PAC File", &["pac"]);
+        match &self.settings.last_dir {
+            Some(dir) => dialog.set_directory(dir),
+            None => dialog,
+        }
+    }
+
+    // Also remembers the view of every open PAC, so it comes back the next time that PAC is opened.
+    fn save_settings(&mut self) {
+        self.settings.sprite_dir = self.sprites.dir().map(Path::to_path_buf);
+        self.settings.sprite_origin = [self.sprites.origin.x, self.sprites.origin.y];
+        for tab in &self.tabs {
+            if tab.has_file {
+                self.settings.remember_view(&tab.path, tab.boxes_window.view());
+            }
+        }
+        if let Err(e) = settings::save(&mut self.settings) {
+            println!("Error writing the settings: {:#}", e);
+        }
+    }
+
+    // Asks for the PAC the active tab was edited from and the updated official PAC, then opens
+    // the merge result in a new tab.
+    fn merge_tab(&mut self) {
+        let base = match self.pac_dialog()
+        .set_title("Choose the original PAC the edit was based on")
+        .pick_file() {
+            Some(path) => path,
+            None => return,
+        };
+        let theirs = match self.pac_dialog()
+        .set_title("Choose the updated PAC")
+        .pick_file() {
+            Some(path) => path,
+            None => return,
+        };
+        let result = match self.tabs.get(self.active) {
+            Some(tab) => tab.boxes_window.merge_onto(&base, &theirs),
+            None => return,
+        };
+        match result {
+            Ok(boxes_window) => {
+                self.tabs.push(Tab { path: theirs, has_file: false, boxes_window });
+                self.active = self.tabs.len() - 1;
+            },
+            Err(e) => self.report(Err(e)),
+        }
+    }
+
+    fn window(&mut self) -> Option<&mut BoxesWindow> {
+        self.tabs.get_mut(self.active).map(|tab| &mut tab.boxes_window)
+    }
+
+    fn tab_bar(&mut self, ui: &mut egui::Ui) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        let mut close = None;
+        // Kept as it is while the save/discard/cancel prompt is open, since the prompt refers to a tab by its index.
+        let enabled = self.pending_close.is_none();
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (index, tab) in self.tabs.iter().enumerate() {
+                    if ui.selectable_label(index == self.active, tab.label()).clicked() {
+                        self.active = index;
+                    }
+                    if ui.small_button("x").on_hover_text("Close").clicked() {
+                        close = Some(index);
+                    }
+                    ui.separator();
+                }
+            });
+        });
+        if let Some(index) = close {
+            self.close_tab(index);
+        }
+        ui.separator();
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        if self.pending_close.is_some() {
+            return;
+        }
+        match self.tabs.get(index) {
+            Some(tab) if tab.boxes_window.is_dirty() => self.pending_close = Some(PendingClose::Tab(index)),
+            Some(_) => self.remove_tab(index),
+            None => {},
+        }
+    }
+
+    fn remove_tab(&mut self, index: usize) {
+        let tab = self.tabs.remove(index);
+        if tab.has_file {
+            self.settings.remember_view(&tab.path, tab.boxes_window.view());
+        }
+        if self.active >= index && self.active > 0 {
+            self.active -= 1;
+        }
+    }
+
+    // Overwrites the file the tab was opened from. Returns whether the tab was saved, so closing can go ahead.
+    fn save_tab(&mut self, index: usize) -> bool {
+        let result = match self.tabs.get_mut(index) {
+            Some(tab) if tab.has_file => tab.boxes_window.write_pac(&tab.path),
+            Some(_) => return self.save_tab_as(index),
+            None => return false,
+        };
+        let saved = result.is_ok();
+        self.report(result);
+        saved
+    }
+
+    fn save_tab_as(&mut self, index: usize) -> bool {
+        let mut dialog = rfd::FileDialog::new().add_filter("PAC File", &["pac"]);
+        if let Some(tab) = self.tabs.get(index) {
+            if let Some(dir) = tab.path.parent() {
+                dialog = dialog.set_directory(dir);
+            }
+            dialog = dialog.set_file_name(&tab.title());
+        }
+        let path = match dialog.save_file() {
+            Some(path) => path.with_extension("pac"),
+            None => return false,
+        };
+        let result = match self.tabs.get_mut(index) {
+            Some(tab) => tab.boxes_window.write_pac(&path),
+            None => return false,
+        };
+        let saved = result.is_ok();
+        if saved {
+            self.settings.add_recent(&path);
+            let tab = &mut self.tabs[index];
+            tab.path = path;
+            tab.has_file = true;
+        }
+        self.report(result);
+        saved
+    }
+
+    fn close_prompt(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let pending = match self.pending_close {
+            Some(pending) => pending,
+            None => return,
+        };
+        let message = match pending {
+            PendingClose::Tab(index) => match self.tabs.get(index) {
+                Some(tab) => format!("{} has unsaved changes.", tab.title()),
+                None => {
+                    self.pending_close = None;
+                    return;
+                },
+            },
+            PendingClose::Window => "Some of the open files have unsaved changes.".to_string(),
+        };
+        let mut choice = None;
+        egui::Window::new("Unsaved changes")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(message);
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    choice = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    choice = Some(false);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.pending_close = None;
+                }
+            });
+        });
+        let save = match choice {
+            Some(save) => save,
+            None => return,
+        };
+        self.pending_close = None;
+        match pending {
+            PendingClose::Tab(index) => {
+                if !save || self.save_tab(index) {
+                    self.remove_tab(index);
+                }
+            },
+            PendingClose::Window => {
+                if save {
+                    for index in 0..self.tabs.len() {
+                        if self.tabs[index].boxes_window.is_dirty() && !self.save_tab(index) {
+                            return;
+                        }
+                    }
+                }
+                self.allow_close = true;
+                self.clear_recovery();
+                self.save_settings();
+                frame.quit();
+            },
+        }
+    }
+
+    // Runs every frame, and `start_autosave_timer` makes sure there is one at least every interval.
+    fn autosave(&mut self) {
+        if self.recovered.is_some() {
+            return;
+        }
+        let now = Instant::now();
+        if self.last_autosave.map_or(false, |last| now.duration_since(last) < AUTOSAVE_INTERVAL) {
+            return;
+        }
+        self.last_autosave = Some(now);
+        let recovery = match &self.recovery {
+            Some(recovery) => recovery,
+            None => return,
+        };
+        if !self.tabs.iter().any(|tab| tab.boxes_window.is_dirty()) {
+            recovery.clear();
+            return;
+        }
+        let session = Session {
+            tabs: self.tabs.iter().map(|tab| tab.boxes_window.session(&tab.path, tab.has_file)).collect(),
+            active: self.active,
+        };
+        if let Err(e) = recovery.save(&session) {
+            println!("Error writing the recovery file: {:#}", e);
+        }
+    }
+
+    fn clear_recovery(&self) {
+        if let Some(recovery) = &self.recovery {
+            recovery.clear();
+        }
+    }
+
+    fn recovery_prompt(&mut self, ctx: &egui::Context) {
+        let mut choice = None;
+        if let Some(orphans) = &self.recovered {
+            egui::Window::new("Recover unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("The editor didn't close properly last time. These files had unsaved changes:");
+                for tab in orphans.sessions.iter().flat_map(|session| &session.tabs) {
+                    ui.monospace(tab.path.display().to_string());
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+        }
+        let restore = match choice {
+            Some(restore) => restore,
+            None => return,
+        };
+        let orphans = self.recovered.take().unwrap();
+        if restore {
+            for session in &orphans.sessions {
+                let first = self.tabs.len();
+                for tab in &session.tabs {
+                    match BoxesWindow::restore_session(tab) {
+                        Ok(boxes_window) => self.tabs.push(Tab { path: tab.path.clone(), has_file: tab.has_file, boxes_window }),
+                        Err(e) => self.report(Err(e)),
+                    }
+                }
+                if self.tabs.len() > first {
+                    self.active = (first + session.active).min(self.tabs.len() - 1);
+                }
+            }
+        }
+        // The restored work goes straight into this editor's own recovery file.
+        orphans.clear();
+        self.last_autosave = None;
+    }
+
+    fn report(&mut self, result: AResult<()>) {
+        if let Err(e) = result {
+            self.error = Some(format!("{:#}", e));
+        }
+    }
+
+    fn error_dialog(&mut self, ctx: &egui::Context) {
+        let mut close = false;
+        if let Some(error) = &self.error {
+            egui::Window::new("Error")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(error);
+                if ui.button("OK").clicked() {
+                    close = true;
+                }
+            });
+        }
+        if close {
+            self.error = None;
+        }
+    }
+
     fn detect_files_being_dropped(&mut self, ctx: &egui::Context) {
         use egui::*;
 
@@ -132,44 +592,202 @@ impl MyApp {
     }
     fn file_menu(&mut self, ui: &mut egui::Ui) {
         if ui.button("Open").clicked() {
-            if let Some(path) = rfd::FileDialog::new()
-            .add_filter("PAC File", &["pac"])
-            .pick_file() {
-                self.success = self.boxes_window.open_file(&path);
-                self.picked_path = Some(path.display().to_string());
+            if let Some(path) = self.pac_dialog().pick_file() {
+                self.open(&path);
             };
             ui.close_menu();
         }
-        if ui.button("Save").clicked() {
-            if !self.boxes_window.jonbins.is_empty() {
+        ui.menu_button("Recent files", |ui| {
+            if self.settings.recent.is_empty() {
+                ui.label("No recent files");
+                return;
+            }
+            for path in self.settings.recent.clone() {
+                if ui.button(path.display().to_string()).clicked() {
+                    self.open(&path);
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            if ui.button("Clear recent files").clicked() {
+                self.settings.recent.clear();
+                self.save_settings();
+                ui.close_menu();
+            }
+        });
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Save (Ctrl+S)")).clicked() {
+            self.save_tab(self.active);
+            ui.close_menu();
+        }
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Save As... (Ctrl+Shift+S)")).clicked() {
+            self.save_tab_as(self.active);
+            ui.close_menu();
+        }
+        ui.separator();
+        let has_selection = self.tabs.get(self.active)
+            .map_or(false, |tab| tab.boxes_window.selected_jonb().is_some());
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Import jonbin")).clicked() {
+            if let Some(paths) = rfd::FileDialog::new()
+            .add_filter("Jonbin File", &["jonbin"])
+            .pick_files() {
+                for path in paths {
+                    let result = match self.window() {
+                        Some(window) => window.import_jonb(&path).map(|_| ()),
+                        None => Ok(()),
+                    };
+                    self.report(result);
+                }
+            };
+            ui.close_menu();
+        }
+        if ui.add_enabled(has_selection, egui::Button::new("Export jonbin")).clicked() {
+            if let Some(window) = self.window() {
+                let mut result = Ok(());
+                let name = window.selected_jonb().map(|(name, _)| name.to_string()).unwrap_or_default();
                 if let Some(path) = rfd::FileDialog::new()
-                .add_filter("PAC File", &["pac"])
+                .add_filter("Jonbin File", &["jonbin"])
+                .set_file_name(&format!("{}.jonbin", name))
                 .save_file() {
-                    self.boxes_window.write_pac(&path).expect("Failed to save file!");
+                    result = window.export_jonb(&path);
                 };
-                ui.close_menu();    
+                self.report(result);
             }
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Export collision data")).clicked() {
+            if let Some(window) = self.window() {
+                let mut result = Ok(());
+                if let Some(path) = rfd::FileDialog::new()
+                .add_filter("JSON", &["json"])
+                .add_filter("YAML", &["yaml", "yml"])
+                .save_file() {
+                    result = window.export_collision(&path);
+                };
+                self.report(result);
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Import collision data")).clicked() {
+            if let Some(window) = self.window() {
+                let mut result = Ok(());
+                if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Collision data", &["json", "yaml", "yml"])
+                .pick_file() {
+                    result = window.import_collision(&path);
+                };
+                self.report(result);
+            }
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Compare with...")).clicked() {
+            if let Some(window) = self.window() {
+                let mut result = Ok(());
+                if let Some(path) = rfd::FileDialog::new()
+                .add_filter("PAC File", &["pac"])
+                .pick_file() {
+                    result = window.compare_with(&path);
+                };
+                self.report(result);
+            }
+            ui.close_menu();
+        }
+        let is_comparing = self.tabs.get(self.active).map_or(false, |tab| tab.boxes_window.is_comparing());
+        if ui.add_enabled(is_comparing, egui::Button::new("Stop comparing")).clicked() {
+            if let Some(window) = self.window() {
+                window.stop_comparing();
+            }
+            ui.close_menu();
+        }
+        if ui.add_enabled(!self.tabs.is_empty(), egui::Button::new("Merge onto updated PAC...")).clicked() {
+            self.merge_tab();
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui.add_enabled(!self.tabs.is_empty() && self.pending_close.is_none(), egui::Button::new("Close")).clicked() {
+            self.close_tab(self.active);
+            ui.close_menu();
         }
     }
-    fn modify_menu(&mut self, ui: &mut egui::Ui) {
-        if ui.button("Add hurtbox").clicked() {
-            self.boxes_window.add_hurtbox();
-            ui.close_menu();    
+    fn edit_menu(&mut self, ui: &mut egui::Ui) {
+        let window = match self.tabs.get_mut(self.active).map(|tab| &mut tab.boxes_window) {
+            Some(window) => window,
+            None => {
+                ui.label("No file is open!");
+                return;
+            },
+        };
+        if ui.add_enabled(window.can_undo(), egui::Button::new("Undo (Ctrl+Z)")).clicked() {
+            window.undo();
+            ui.close_menu();
         }
-        if ui.button("Add hitbox").clicked() {
-            self.boxes_window.add_hitbox();
-            ui.close_menu();    
-        }            
-        if ui.button("Remove hurtbox").clicked() {
-            self.boxes_window.remove_hurtbox();
-            ui.close_menu();    
+        if ui.add_enabled(window.can_redo(), egui::Button::new("Redo (Ctrl+Shift+Z)")).clicked() {
+            window.redo();
+            ui.close_menu();
         }
-        if ui.button("Remove hitbox").clicked() {
-            self.boxes_window.remove_hitbox();
-            ui.close_menu();    
+        ui.separator();
+        if ui.add_enabled(window.selected_jonb().is_some(), egui::Button::new("Copy jonbin")).clicked() {
+            if let Some((name, jonb)) = window.selected_jonb() {
+                self.clipboard = Some((name.to_string(), clone_jonb(jonb)));
+            }
+            ui.close_menu();
+        }
+        let paste_label = match &self.clipboard {
+            Some((name, _)) => format!("Paste jonbin ({})", name),
+            None => "Paste jonbin".to_string(),
+        };
+        if ui.add_enabled(self.clipboard.is_some(), egui::Button::new(paste_label)).clicked() {
+            if let Some((name, jonb)) = &self.clipboard {
+                window.paste_jonb(name, clone_jonb(jonb));
+            }
+            ui.close_menu();
+        }
+    }
+    fn modify_menu(&mut self, ui: &mut egui::Ui) {
+        let window = match self.window() {
+            Some(window) => window,
+            None => {
+                ui.label("No file is open!");
+                return;
+            },
+        };
+        ui.menu_button("Add box", |ui| {
+            for box_type in BoxType::ALL {
+                if ui.add_enabled(window.has_box_group(box_type), egui::Button::new(box_type.name())).clicked() {
+                    window.add_box(box_type);
+                    ui.close_menu();
+                }
+            }
+        });
+        if ui.add_enabled(window.has_selected_box(), egui::Button::new("Delete selected box")).clicked() {
+            window.remove_selected_box();
+            ui.close_menu();
         }
     }
     fn settings(&mut self, ui: &mut egui::Ui) {
-        ui.checkbox(&mut self.boxes_window.is_gbvs, "Granblue Fantasy Versus");
+        if ui.checkbox(&mut self.is_gbvs, "Granblue Fantasy Versus").changed() {
+            self.settings.is_gbvs = self.is_gbvs;
+            for tab in &mut self.tabs {
+                tab.boxes_window.is_gbvs = self.is_gbvs;
+            }
+        }
+        ui.separator();
+        let sprites = &mut self.sprites;
+        if ui.button("Choose sprite folder").clicked() {
+            if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                sprites.set_dir(dir);
+            }
+            ui.close_menu();
+        }
+        if let Some(dir) = sprites.dir() {
+            ui.label(format!("Sprites: {}", dir.display()));
+        }
+        ui.checkbox(&mut sprites.show, "Show sprites");
+        ui.horizontal(|ui| {
+            ui.label("Sprite origin");
+            ui.add(egui::DragValue::new(&mut sprites.origin.x).prefix("x: "));
+            ui.add(egui::DragValue::new(&mut sprites.origin.y).prefix("y: "));
+        });
     }
 }
@@ -0,0 +1,345 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt};
+use arcsys::ggst::jonbin::{GGSTJonBin, HitBox};
+
+use crate::boxes::clone_jonb;
+use crate::diff::{same_box, same_jonb};
+
+// Something both our edit and the new PAC changed differently. The merged PAC keeps their
+// version until the conflict is resolved.
+pub enum Conflict {
+    // `index` is where the box sits in the merged group, or where ours would be inserted
+    // if the new PAC removed it.
+    Box { jonbin: String, group: usize, index: usize, ours: Option<HitBox>, theirs: Option<HitBox> },
+    Names { jonbin: String, ours: Vec<String>, theirs: Vec<String> },
+    // One side removed the jonbin while the other changed it, or both added it differently.
+    Jonbin { jonbin: String, ours: Option<GGSTJonBin>, theirs: bool },
+}
+
+impl Conflict {
+    pub fn jonbin(&self) -> &str {
+        match self {
+            Conflict::Box { jonbin, .. } => jonbin,
+            Conflict::Names { jonbin, .. } => jonbin,
+            Conflict::Jonbin { jonbin, .. } => jonbin,
+        }
+    }
+}
+
+fn describe(hitbox: &Option<HitBox>) -> String {
+    match hitbox {
+        Some(hitbox) => {
+            let text = format!("x={} y={} w={} h={}",
+                hitbox.rect.x_offset, hitbox.rect.y_offset, hitbox.rect.width, hitbox.rect.height);
+            match hitbox.extra {
+                Some(extra) => format!("{} extra={}", text, extra),
+                None => text,
+            }
+        },
+        None => "removed".to_string(),
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Box { jonbin, group, index, ours, theirs } => write!(f, "{} [{}:{}]: ours {}, theirs {}",
+                jonbin, group, index, describe(ours), describe(theirs)),
+            Conflict::Names { jonbin, ours, theirs } => write!(f, "{} images: ours {:?}, theirs {:?}",
+                jonbin, ours, theirs),
+            Conflict::Jonbin { jonbin, ours, theirs } => {
+                let ours = if ours.is_some() { "changed" } else { "removed" };
+                let theirs = if *theirs { "changed" } else { "removed" };
+                write!(f, "{}: ours {}, theirs {}", jonbin, ours, theirs)
+            },
+        }
+    }
+}
+
+fn same_opt(a: Option<&HitBox>, b: Option<&HitBox>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_box(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// Reapplies the changes from `base` to `ours` onto `theirs`, which becomes the merged set of jonbins.
+pub fn merge(
+    base: &BTreeMap<String, GGSTJonBin>,
+    ours: &BTreeMap<String, GGSTJonBin>,
+    theirs: &mut BTreeMap<String, GGSTJonBin>,
+) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let names: BTreeSet<String> = base.keys().chain(ours.keys()).chain(theirs.keys()).cloned().collect();
+    for name in names {
+        match (base.get(&name), ours.get(&name), theirs.contains_key(&name)) {
+            (Some(base_jonb), Some(our_jonb), true) => {
+                let their_jonb = theirs.get_mut(&name).unwrap();
+                merge_jonb(&name, base_jonb, our_jonb, their_jonb, &mut conflicts);
+            },
+            (Some(base_jonb), Some(our_jonb), false) => {
+                if !same_jonb(base_jonb, our_jonb) {
+                    conflicts.push(Conflict::Jonbin { jonbin: name, ours: Some(clone_jonb(our_jonb)), theirs: false });
+                }
+            },
+            (Some(base_jonb), None, true) => {
+                if same_jonb(base_jonb, &theirs[&name]) {
+                    theirs.remove(&name);
+                }
+                else {
+                    conflicts.push(Conflict::Jonbin { jonbin: name, ours: None, theirs: true });
+                }
+            },
+            (None, Some(our_jonb), false) => {
+                theirs.insert(name, clone_jonb(our_jonb));
+            },
+            (None, Some(our_jonb), true) => {
+                if !same_jonb(our_jonb, &theirs[&name]) {
+                    conflicts.push(Conflict::Jonbin { jonbin: name, ours: Some(clone_jonb(our_jonb)), theirs: true });
+                }
+            },
+            _ => {},
+        }
+    }
+    conflicts
+}
+
+fn merge_jonb(name: &str, base: &GGSTJonBin, ours: &GGSTJonBin, theirs: &mut GGSTJonBin, conflicts: &mut Vec<Conflict>) {
+    if ours.names != base.names && ours.names != theirs.names {
+        if theirs.names == base.names {
+            theirs.names = ours.names.clone();
+        }
+        else {
+            conflicts.push(Conflict::Names { jonbin: name.to_string(), ours: ours.names.clone(), theirs: theirs.names.clone() });
+        }
+    }
+
+    let group_count = base.boxes.len().max(ours.boxes.len()).max(theirs.boxes.len());
+    let mut boxes = Vec::with_capacity(group_count);
+    for group in 0..group_count {
+        let base_group = base.boxes.get(group).map(|group| group.as_slice()).unwrap_or(&[]);
+        let our_group = ours.boxes.get(group).map(|group| group.as_slice()).unwrap_or(&[]);
+        let their_group = theirs.boxes.get(group).map(|group| group.as_slice()).unwrap_or(&[]);
+        boxes.push(merge_group(name, group, base_group, our_group, their_group, conflicts));
+    }
+    theirs.boxes = boxes;
+}
+
+// Boxes are matched by index, like in the diff.
+fn merge_group(
+    name: &str,
+    group: usize,
+    base: &[HitBox],
+    ours: &[HitBox],
+    theirs: &[HitBox],
+    conflicts: &mut Vec<Conflict>,
+) -> Vec<HitBox> {
+    let box_count = base.len().max(ours.len()).max(theirs.len());
+    let mut merged = Vec::with_capacity(box_count);
+    for index in 0..box_count {
+        let (base_box, our_box, their_box) = (base.get(index), ours.get(index), theirs.get(index));
+        let picked = if same_opt(our_box, base_box) {
+            their_box
+        }
+        else if same_opt(their_box, base_box) || same_opt(our_box, their_box) {
+            our_box
+        }
+        else {
+            conflicts.push(Conflict::Box {
+                jonbin: name.to_string(),
+                group,
+                index: merged.len(),
+                ours: our_box.copied(),
+                theirs: their_box.copied(),
+            });
+            their_box
+        };
+        if let Some(hitbox) = picked {
+            merged.push(*hitbox);
+        }
+    }
+    merged
+}
+
+// Applies our side of a conflict to the merged jonbins. Box conflicts later in the same group
+// are shifted when a box is inserted or removed before them.
+pub fn use_ours(conflict: Conflict, jonbins: &mut BTreeMap<String, GGSTJonBin>, rest: &mut [Conflict]) {
+    match conflict {
+        Conflict::Box { .. } => {
+            if let Some(jonb) = jonbins.get_mut(conflict.jonbin()) {
+                use_our_box(&mut jonb.boxes, &conflict, rest);
+            }
+        },
+        Conflict::Names { jonbin, ours, .. } => {
+            if let Some(jonb) = jonbins.get_mut(&jonbin) {
+                jonb.names = ours;
+            }
+        },
+        Conflict::Jonbin { jonbin, ours, .. } => {
+            match ours {
+                Some(jonb) => {
+                    jonbins.insert(jonbin, jonb);
+                },
+                None => {
+                    jonbins.remove(&jonbin);
+                },
+            }
+        },
+    }
+}
+
+// The box part of `use_ours`, on the box groups of the conflict's jonbin.
+fn use_our_box(boxes: &mut Vec<Vec<HitBox>>, conflict: &Conflict, rest: &mut [Conflict]) {
+    let (jonbin, group, index, ours, theirs) = match conflict {
+        Conflict::Box { jonbin, group, index, ours, theirs } => (jonbin, *group, *index, *ours, *theirs),
+        _ => return,
+    };
+    while boxes.len() <= group {
+        boxes.push(Vec::new());
+    }
+    let boxgroup = &mut boxes[group];
+    let shift: isize = match (ours, theirs) {
+        (Some(ours), Some(_)) if index < boxgroup.len() => {
+            boxgroup[index] = ours;
+            0
+        },
+        (Some(ours), None) => {
+            boxgroup.insert(index.min(boxgroup.len()), ours);
+            1
+        },
+        (None, Some(_)) if index < boxgroup.len() => {
+            boxgroup.remove(index);
+            -1
+        },
+        _ => 0,
+    };
+    for other in rest {
+        if let Conflict::Box { jonbin: other_jonbin, group: other_group, index: other_index, .. } = other {
+            let after = if shift > 0 { *other_index >= index } else { *other_index > index };
+            if other_jonbin == jonbin && *other_group == group && after {
+                *other_index = (*other_index as isize + shift) as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arcsys::ggst::jonbin::Rect;
+    use super::*;
+
+    // Boxes are told apart by their x offset.
+    fn hitbox(x: f32) -> HitBox {
+        HitBox { kind: 0, rect: Rect { x_offset: x, y_offset: 0.0, width: 10.0, height: 10.0 }, extra: None }
+    }
+
+    fn xs(boxes: &[HitBox]) -> Vec<f32> {
+        boxes.iter().map(|hitbox| hitbox.rect.x_offset).collect()
+    }
+
+    // Resolves the first remaining conflict with our side, like the conflict panel does.
+    fn resolve_first(boxes: &mut Vec<Vec<HitBox>>, conflicts: &mut Vec<Conflict>) {
+        let conflict = conflicts.remove(0);
+        use_our_box(boxes, &conflict, conflicts);
+    }
+
+    #[test]
+    fn keeps_our_change() {
+        let mut conflicts = Vec::new();
+        let merged = merge_group("a", 0, &[hitbox(1.0)], &[hitbox(2.0)], &[hitbox(1.0)], &mut conflicts);
+        assert_eq!(xs(&merged), vec![2.0]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_their_change() {
+        let mut conflicts = Vec::new();
+        let merged = merge_group("a", 0, &[hitbox(1.0)], &[hitbox(1.0)], &[hitbox(3.0)], &mut conflicts);
+        assert_eq!(xs(&merged), vec![3.0]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_our_extra_change() {
+        let mut conflicts = Vec::new();
+        let base = HitBox { extra: Some(0), ..hitbox(1.0) };
+        let ours = HitBox { extra: Some(5), ..hitbox(1.0) };
+        let merged = merge_group("a", 0, &[base], &[ours], &[base], &mut conflicts);
+        assert_eq!(merged[0].extra, Some(5));
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn both_changed_conflicts() {
+        let mut conflicts = Vec::new();
+        let merged = merge_group("a", 0, &[hitbox(1.0)], &[hitbox(2.0)], &[hitbox(3.0)], &mut conflicts);
+        assert_eq!(xs(&merged), vec![3.0]);
+        assert_eq!(conflicts.len(), 1);
+        match &conflicts[0] {
+            Conflict::Box { index, ours, theirs, .. } => {
+                assert_eq!(*index, 0);
+                assert_eq!(ours.map(|hitbox| hitbox.rect.x_offset), Some(2.0));
+                assert_eq!(theirs.map(|hitbox| hitbox.rect.x_offset), Some(3.0));
+            },
+            _ => panic!("expected a box conflict"),
+        }
+
+        let mut boxes = vec![merged];
+        resolve_first(&mut boxes, &mut conflicts);
+        assert_eq!(xs(&boxes[0]), vec![2.0]);
+    }
+
+    #[test]
+    fn their_removal_conflicts_with_our_change() {
+        let mut conflicts = Vec::new();
+        let base = [hitbox(1.0), hitbox(2.0)];
+        let ours = [hitbox(1.0), hitbox(4.0)];
+        let merged = merge_group("a", 0, &base, &ours, &[hitbox(1.0)], &mut conflicts);
+        assert_eq!(xs(&merged), vec![1.0]);
+        assert_eq!(conflicts.len(), 1);
+        match &conflicts[0] {
+            Conflict::Box { index, ours, theirs, .. } => {
+                assert_eq!(*index, 1);
+                assert!(ours.is_some());
+                assert!(theirs.is_none());
+            },
+            _ => panic!("expected a box conflict"),
+        }
+
+        let mut boxes = vec![merged];
+        resolve_first(&mut boxes, &mut conflicts);
+        assert_eq!(xs(&boxes[0]), vec![1.0, 4.0]);
+    }
+
+    #[test]
+    fn resolving_insertions_shifts_later_conflicts() {
+        // They removed every box while we changed the first and last.
+        let mut conflicts = Vec::new();
+        let base = [hitbox(1.0), hitbox(2.0), hitbox(3.0)];
+        let ours = [hitbox(10.0), hitbox(2.0), hitbox(30.0)];
+        let merged = merge_group("a", 0, &base, &ours, &[], &mut conflicts);
+        assert!(merged.is_empty());
+        assert_eq!(conflicts.len(), 2);
+
+        let mut boxes = vec![merged];
+        resolve_first(&mut boxes, &mut conflicts);
+        resolve_first(&mut boxes, &mut conflicts);
+        assert_eq!(xs(&boxes[0]), vec![10.0, 30.0]);
+    }
+
+    #[test]
+    fn resolving_removals_shifts_later_conflicts() {
+        // We removed both boxes while they changed them.
+        let mut conflicts = Vec::new();
+        let base = [hitbox(1.0), hitbox(2.0), hitbox(3.0)];
+        let theirs = [hitbox(10.0), hitbox(20.0), hitbox(3.0)];
+        let merged = merge_group("a", 0, &base, &[], &theirs, &mut conflicts);
+        assert_eq!(xs(&merged), vec![10.0, 20.0]);
+        assert_eq!(conflicts.len(), 2);
+
+        let mut boxes = vec![merged];
+        resolve_first(&mut boxes, &mut conflicts);
+        resolve_first(&mut boxes, &mut conflicts);
+        assert!(boxes[0].is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use std::{collections::BTreeMap, fs::{self, File, OpenOptions}, path::{Path, PathBuf}, process, time::{SystemTime, UNIX_EPOCH}};
+use serde::{Serialize, Deserialize};
+use anyhow::Result as AResult;
+
+use crate::save::{data_dir, write_atomic};
+use crate::settings::ViewState;
+
+// Unsaved work from every open tab, written periodically so it survives a crash.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub tabs: Vec<TabSession>,
+    pub active: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TabSession {
+    pub path: PathBuf,
+    pub has_file: bool,
+    pub is_gbvs: bool,
+    // Only the jonbins that differ from the file, as jonbin bytes. `None` marks a removed jonbin.
+    pub jonbins: BTreeMap<String, Option<Vec<u8>>>,
+    #[serde(flatten)]
+    pub view: ViewState,
+}
+
+// Every running editor writes its own `recovery-<id>.json` and holds a lock on `recovery-<id>.lock`
+// until it exits, so the files of an editor that is still running are never offered or removed.
+pub struct Recovery {
+    path: PathBuf,
+    _lock: File,
+}
+
+impl Recovery {
+    pub fn new() -> Option<Self> {
+        let dir = recovery_dir()?;
+        if let Err(e) = fs::create_dir_all(&dir) {
+            println!("Error creating the recovery folder: {}", e);
+            return None;
+        }
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_millis());
+        let id = format!("{}-{}", process::id(), millis);
+        let lock = match open_lock(&dir.join(format!("recovery-{}.lock", id))) {
+            Some(lock) => lock,
+            None => {
+                println!("Error locking the recovery file, unsaved changes won't be autosaved");
+                return None;
+            },
+        };
+        Some(Self { path: dir.join(format!("recovery-{}.json", id)), _lock: lock })
+    }
+
+    pub fn save(&self, session: &Session) -> AResult<()> {
+        write_atomic(&self.path, &serde_json::to_vec(session)?)
+    }
+
+    pub fn clear(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Sessions left behind by editors that aren't running anymore. Their locks are held until the
+// user restored or discarded them, so another editor starting meanwhile doesn't offer them too.
+pub struct Orphans {
+    pub sessions: Vec<Session>,
+    paths: Vec<PathBuf>,
+    locks: Vec<(PathBuf, File)>,
+}
+
+impl Orphans {
+    pub fn clear(self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+        // Windows won't remove a file that is still open, so the locks are released first.
+        for (path, lock) in self.locks {
+            drop(lock);
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// Recovery files whose lock can be taken belong to an editor that closed without clearing them.
+pub fn find_orphans() -> Option<Orphans> {
+    let entries = fs::read_dir(recovery_dir()?).ok()?;
+    let mut orphans = Orphans { sessions: Vec::new(), paths: Vec::new(), locks: Vec::new() };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("lock") {
+            continue;
+        }
+        let lock = match open_lock(&path) {
+            Some(lock) => lock,
+            None => continue,
+        };
+        let session_path = path.with_extension("json");
+        if let Some(session) = load(&session_path) {
+            orphans.sessions.push(session);
+        }
+        orphans.paths.push(session_path);
+        orphans.locks.push((path, lock));
+    }
+    // Editors that closed cleanly only leave an unlocked lock file behind, with nothing to offer.
+    if orphans.sessions.is_empty() {
+        orphans.clear();
+        return None;
+    }
+    Some(orphans)
+}
+
+fn recovery_dir() -> Option<PathBuf> {
+    Some(data_dir()?.join("recovery"))
+}
+
+// Fails when another editor holds the lock.
+fn open_lock(path: &Path) -> Option<File> {
+    let file = OpenOptions::new().create(true).write(true).truncate(false).open(path).ok()?;
+    file.try_lock().ok()?;
+    Some(file)
+}
+
+fn load(path: &Path) -> Option<Session> {
+    let bytes = fs::read(path).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(session) => Some(session),
+        Err(e) => {
+            println!("Error reading the recovery file {}: {}", path.display(), e);
+            None
+        },
+    }
+}
@@ -0,0 +1,135 @@
+use std::{env, fs::{self, File}, io::Write, path::{Path, PathBuf}};
+use anyhow::{Context, Result as AResult};
+
+// The per-user folder for the editor's own files, like settings and the recovery file.
+pub fn data_dir() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    }
+    else {
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+    }?;
+    Some(dir.join("ggst_collision_editor_rs"))
+}
+
+// How many `.bak` copies of a file are kept, the newest being `<file>.bak`.
+const BACKUPS: usize = 3;
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => with_suffix(path, ".bak"),
+        _ => with_suffix(path, &format!(".bak{}", index)),
+    }
+}
+
+// Shifts the older backups up by one and copies the current file into `<file>.bak`.
+fn rotate_backups(path: &Path) -> AResult<()> {
+    for index in (0..BACKUPS - 1).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 0))?;
+    Ok(())
+}
+
+// Writes next to the destination first, so a failed write never touches the destination.
+fn write_temp(path: &Path, bytes: &[u8]) -> AResult<PathBuf> {
+    let temp_path = with_suffix(path, ".tmp");
+    let written = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e).with_context(|| format!("Failed to write {}", temp_path.display()));
+    }
+    Ok(temp_path)
+}
+
+// The destination is either left untouched or fully replaced.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> AResult<()> {
+    let temp_path = write_temp(path, bytes)?;
+    fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+pub fn write_with_backup(path: &Path, bytes: &[u8]) -> AResult<()> {
+    let temp_path = write_temp(path, bytes)?;
+    if path.exists() {
+        if let Err(e) = rotate_backups(path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e).with_context(|| format!("Failed to back up {}", path.display()));
+        }
+    }
+    fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh folder per test, so tests running in parallel don't see each other's files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ggst_collision_editor_rs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn first_save_has_no_backup() {
+        let dir = test_dir("first_save");
+        let path = dir.join("test.pac");
+        write_with_backup(&path, b"new").unwrap();
+        assert_eq!(read(&path), "new");
+        assert!(!backup_path(&path, 0).exists());
+        assert!(!with_suffix(&path, ".tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_newest_backups() {
+        let dir = test_dir("rotate");
+        let path = dir.join("test.pac");
+        for version in 0..BACKUPS + 2 {
+            write_with_backup(&path, version.to_string().as_bytes()).unwrap();
+        }
+        let newest = BACKUPS + 1;
+        assert_eq!(read(&path), newest.to_string());
+        for index in 0..BACKUPS {
+            assert_eq!(read(&backup_path(&path, index)), (newest - 1 - index).to_string());
+        }
+        assert!(!backup_path(&path, BACKUPS).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_write_changes_nothing() {
+        let dir = test_dir("failed_write");
+        let path = dir.join("test.pac");
+        write_with_backup(&path, b"old").unwrap();
+        write_with_backup(&path, b"current").unwrap();
+        // A folder in the way of the temporary file makes the write fail.
+        fs::create_dir(with_suffix(&path, ".tmp")).unwrap();
+        assert!(write_with_backup(&path, b"new").is_err());
+        assert_eq!(read(&path), "current");
+        assert_eq!(read(&backup_path(&path, 0)), "old");
+        assert!(!backup_path(&path, 1).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
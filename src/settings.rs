@@ -0,0 +1,103 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+use serde::{Serialize, Deserialize};
+use anyhow::{anyhow, Result as AResult};
+
+use crate::save::{data_dir, write_atomic};
+
+// How many files the File > Recent files menu keeps.
+const RECENT_FILES: usize = 10;
+
+// Everything the editor remembers between runs.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub is_gbvs: bool,
+    pub dark_mode: bool,
+    pub sprite_dir: Option<PathBuf>,
+    pub sprite_origin: [f32; 2],
+    // Where the last PAC was opened from, so file dialogs start there.
+    pub last_dir: Option<PathBuf>,
+    // Newest first.
+    pub recent: Vec<PathBuf>,
+    // Keyed by the PAC's path. Only the recent files keep theirs.
+    pub views: BTreeMap<String, ViewState>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            is_gbvs: false,
+            dark_mode: true,
+            sprite_dir: None,
+            sprite_origin: [0.0, 0.0],
+            last_dir: None,
+            recent: Vec::new(),
+            views: BTreeMap::new(),
+        }
+    }
+}
+
+// Where a PAC was left: the selected jonbin and the canvas position.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ViewState {
+    pub selected: String,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub zoom: f32,
+}
+
+impl Settings {
+    pub fn add_recent(&mut self, path: &Path) {
+        self.recent.retain(|other| other != path);
+        self.recent.insert(0, path.to_path_buf());
+        self.recent.truncate(RECENT_FILES);
+        if let Some(dir) = path.parent() {
+            self.last_dir = Some(dir.to_path_buf());
+        }
+    }
+
+    pub fn remove_recent(&mut self, path: &Path) {
+        self.recent.retain(|other| other != path);
+    }
+
+    pub fn view(&self, path: &Path) -> Option<&ViewState> {
+        self.views.get(&view_key(path))
+    }
+
+    pub fn remember_view(&mut self, path: &Path, view: ViewState) {
+        self.views.insert(view_key(path), view);
+    }
+}
+
+fn view_key(path: &Path) -> String {
+    path.display().to_string()
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("settings.json"))
+}
+
+// Falls back to the defaults when there are no settings yet or they can't be read.
+pub fn load() -> Settings {
+    let bytes = match settings_path().and_then(|path| fs::read(path).ok()) {
+        Some(bytes) => bytes,
+        None => return Settings::default(),
+    };
+    match serde_json::from_slice(&bytes) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("Error reading the settings: {}", e);
+            Settings::default()
+        },
+    }
+}
+
+pub fn save(settings: &mut Settings) -> AResult<()> {
+    let recent: Vec<String> = settings.recent.iter().map(|path| view_key(path)).collect();
+    settings.views.retain(|key, _| recent.contains(key));
+    let path = settings_path().ok_or_else(|| anyhow!("Couldn't find a folder for the settings!"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_atomic(&path, &serde_json::to_vec_pretty(settings)?)
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+use eframe::{egui::{self, ColorImage, TextureHandle}, emath::Vec2};
+
+pub struct SpriteCache {
+    dir: Option<PathBuf>,
+    pub show: bool,
+    // Where the bottom-center of a sprite sits in jonbin space. Sprites stand on the character origin by default.
+    pub origin: Vec2,
+    textures: HashMap<String, Option<TextureHandle>>,
+}
+
+impl Default for SpriteCache {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            show: true,
+            origin: Vec2::ZERO,
+            textures: Default::default(),
+        }
+    }
+}
+
+impl SpriteCache {
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    pub fn set_dir(&mut self, dir: PathBuf) {
+        self.dir = Some(dir);
+        self.textures.clear();
+    }
+
+    pub fn get(&mut self, ctx: &egui::Context, name: &str) -> Option<&TextureHandle> {
+        let dir = self.dir.as_ref()?;
+        self.textures.entry(name.to_string())
+            .or_insert_with(|| load_sprite(ctx, dir, name))
+            .as_ref()
+    }
+}
+
+fn load_sprite(ctx: &egui::Context, dir: &Path, name: &str) -> Option<TextureHandle> {
+    let path = if name.to_lowercase().ends_with(".png") {
+        dir.join(name)
+    }
+    else {
+        dir.join(format!("{}.png", name))
+    };
+    let image = match image::open(&path) {
+        Ok(image) => image.to_rgba8(),
+        Err(e) => {
+            println!("Error reading sprite {}: {}", path.display(), e);
+            return None;
+        },
+    };
+    let size = [image.width() as usize, image.height() as usize];
+    let pixels = image.into_raw();
+    Some(ctx.load_texture(name, ColorImage::from_rgba_unmultiplied(size, &pixels)))
+}
@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashMap};
+use arcsys::ggst::jonbin::GGSTJonBin;
+use eframe::egui;
+
+use crate::browser::group_key;
+
+// Plays the frames of the selected jonbin's move in order.
+pub struct Timeline {
+    playing: bool,
+    fps: f32,
+    // How many game frames each jonbin is shown for. Jonbins that aren't listed last one frame.
+    durations: HashMap<String, u32>,
+    elapsed: f32,
+    pub onion_skin: bool,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            fps: 60.0,
+            durations: HashMap::new(),
+            elapsed: 0.0,
+            onion_skin: false,
+        }
+    }
+}
+
+impl Timeline {
+    // Returns the jonbin to switch to, when a frame was picked or playback moved on.
+    pub fn ui(&mut self, ui: &mut egui::Ui, jonbins: &BTreeMap<String, GGSTJonBin>, selected: &str) -> Option<String> {
+        let frames = sequence(jonbins, selected);
+        let current = frames.iter().position(|name| *name == selected);
+        let mut switch_to = None;
+
+        ui.horizontal(|ui| {
+            let label = if self.playing { "Pause" } else { "Play" };
+            if ui.add_enabled(frames.len() > 1, egui::Button::new(label)).clicked() {
+                self.playing = !self.playing;
+                self.elapsed = 0.0;
+            }
+            ui.add(egui::DragValue::new(&mut self.fps).clamp_range(1.0..=120.0).speed(1.0).suffix(" fps"));
+            ui.checkbox(&mut self.onion_skin, "Onion skin");
+            if let Some(current) = current {
+                let duration = self.durations.entry(frames[current].to_string()).or_insert(1);
+                ui.label("Frame duration");
+                ui.add(egui::DragValue::new(duration).clamp_range(1..=999).suffix(" f"));
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            for (index, name) in frames.iter().enumerate() {
+                if ui.selectable_label(Some(index) == current, index.to_string())
+                    .on_hover_text(*name)
+                    .clicked()
+                {
+                    self.playing = false;
+                    switch_to = Some(name.to_string());
+                }
+            }
+        });
+
+        if self.playing && frames.len() > 1 {
+            self.elapsed += ui.input().stable_dt;
+            let current = current.unwrap_or(0);
+            let duration = self.duration(frames[current]) as f32 / self.fps;
+            if self.elapsed >= duration {
+                self.elapsed -= duration;
+                switch_to = Some(frames[(current + 1) % frames.len()].to_string());
+            }
+            ui.ctx().request_repaint();
+        }
+        switch_to
+    }
+
+    fn duration(&self, name: &str) -> u32 {
+        self.durations.get(name).copied().unwrap_or(1)
+    }
+}
+
+// The jonbins making up the same move as `name`, in frame order.
+pub fn sequence<'a>(jonbins: &'a BTreeMap<String, GGSTJonBin>, name: &str) -> Vec<&'a str> {
+    let key = group_key(name);
+    jonbins.keys().filter(|other| group_key(other) == key).map(|other| other.as_str()).collect()
+}
+
+// The frame before `name` in its move. The first frame has none.
+pub fn previous_frame<'a>(jonbins: &'a BTreeMap<String, GGSTJonBin>, name: &str) -> Option<&'a str> {
+    let frames = sequence(jonbins, name);
+    let index = frames.iter().position(|other| *other == name)?;
+    frames.get(index.checked_sub(1)?).copied()
+}